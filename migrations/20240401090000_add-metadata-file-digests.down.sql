@@ -0,0 +1,5 @@
+-- Add down migration script here
+
+ALTER TABLE release_files
+    DROP COLUMN metadata_file_sha256_digest,
+    DROP COLUMN metadata_file_blake2_256_digest;
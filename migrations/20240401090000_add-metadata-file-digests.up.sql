@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- PEP 658 - Serve Distribution Metadata in the Simple Repository API
+ALTER TABLE release_files
+    ADD COLUMN metadata_file_sha256_digest CITEXT CHECK (metadata_file_sha256_digest ~* '^[A-F0-9]{64}$'),
+    ADD COLUMN metadata_file_blake2_256_digest CITEXT CHECK (metadata_file_blake2_256_digest ~* '^[A-F0-9]{64}$');
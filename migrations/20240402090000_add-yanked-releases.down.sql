@@ -0,0 +1,9 @@
+-- Add down migration script here
+
+ALTER TABLE releases
+    DROP COLUMN yanked,
+    DROP COLUMN yanked_reason;
+
+ALTER TABLE release_files
+    DROP COLUMN yanked,
+    DROP COLUMN yanked_reason;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+
+-- PEP 592 - Adding "Yank" Support to the Simple API
+ALTER TABLE releases
+    ADD COLUMN yanked BOOL NOT NULL DEFAULT FALSE,
+    ADD COLUMN yanked_reason TEXT;
+
+ALTER TABLE release_files
+    ADD COLUMN yanked BOOL NOT NULL DEFAULT FALSE,
+    ADD COLUMN yanked_reason TEXT;
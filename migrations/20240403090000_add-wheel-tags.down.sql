@@ -0,0 +1,9 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS idx_release_files_platform_tags;
+
+ALTER TABLE release_files
+    DROP COLUMN build_tag,
+    DROP COLUMN python_tags,
+    DROP COLUMN abi_tags,
+    DROP COLUMN platform_tags;
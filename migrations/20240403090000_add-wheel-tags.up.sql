@@ -0,0 +1,10 @@
+-- Add up migration script here
+
+-- PEP 427 - Tags of the wheel, compressed tag sets are expanded.
+ALTER TABLE release_files
+    ADD COLUMN build_tag TEXT,
+    ADD COLUMN python_tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN abi_tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN platform_tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_release_files_platform_tags ON release_files USING GIN (platform_tags);
@@ -0,0 +1,10 @@
+-- Add down migration script here
+
+CREATE OR REPLACE FUNCTION pep440_is_prerelease(text)
+    RETURNS boolean as
+    $$
+        SELECT lower($1) ~* '(a|b|rc|dev|alpha|beta|c|pre|preview)'
+    $$
+    LANGUAGE SQL
+    IMMUTABLE
+    RETURNS NULL ON NULL INPUT;
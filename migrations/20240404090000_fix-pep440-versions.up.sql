@@ -0,0 +1,18 @@
+-- Add up migration script here
+
+-- PEP 440 - A version is a pre-release when it has a pre-release segment
+-- right after the release segment, or a development release segment.
+-- Letters of the local version label are ignored.
+CREATE OR REPLACE FUNCTION pep440_is_prerelease(text)
+    RETURNS boolean as
+    $$
+        SELECT $1 ~* '^\s*v?([0-9]+!)?[0-9]+(\.[0-9]+)*([-_.]?(alpha|a|beta|b|preview|pre|c|rc)|[^+]*dev[-_.]?[0-9]*\s*(\+.*)?$)'
+    $$
+    LANGUAGE SQL
+    IMMUTABLE
+    RETURNS NULL ON NULL INPUT;
+
+UPDATE releases SET is_prerelease = pep440_is_prerelease(version);
+
+-- Canonical versions of the existing releases are recomputed by the
+-- application on startup, with the full PEP 440 normalization.
@@ -0,0 +1,11 @@
+-- Add down migration script here
+
+DROP INDEX IF EXISTS idx_release_dependencies_name;
+DROP INDEX IF EXISTS idx_release_dependencies_release_id;
+
+ALTER TABLE release_dependencies
+    DROP COLUMN name,
+    DROP COLUMN extras,
+    DROP COLUMN version_specifier,
+    DROP COLUMN url,
+    DROP COLUMN marker;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+
+-- PEP 508 - Requirements are stored parsed next to the raw specifier.
+-- Dependencies uploaded before this migration only keep the raw specifier.
+ALTER TABLE release_dependencies
+    ADD COLUMN name TEXT,
+    ADD COLUMN extras TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN version_specifier TEXT,
+    ADD COLUMN url TEXT,
+    ADD COLUMN marker TEXT;
+
+CREATE INDEX idx_release_dependencies_name ON release_dependencies (name);
+CREATE INDEX idx_release_dependencies_release_id ON release_dependencies (release_id);
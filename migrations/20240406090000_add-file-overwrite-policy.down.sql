@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE projects
+    DROP COLUMN allow_file_overwrite;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+
+-- Files are immutable unless an administrator allows overwrites for the project.
+ALTER TABLE projects
+    ADD COLUMN allow_file_overwrite BOOL NOT NULL DEFAULT FALSE;
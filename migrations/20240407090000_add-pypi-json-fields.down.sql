@@ -0,0 +1,10 @@
+-- Add down migration script here
+
+ALTER TABLE release_files
+    ALTER COLUMN upload_time TYPE DATE USING upload_time::DATE,
+    ALTER COLUMN upload_time SET DEFAULT CURRENT_DATE;
+
+ALTER TABLE releases
+    DROP COLUMN classifiers,
+    DROP COLUMN project_urls,
+    DROP COLUMN provides_extras;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+
+-- Core metadata fields served by the PyPI JSON API.
+ALTER TABLE releases
+    ADD COLUMN classifiers TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN project_urls TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN provides_extras TEXT[] NOT NULL DEFAULT '{}';
+
+-- Upload times are reported with their time of day.
+ALTER TABLE release_files
+    ALTER COLUMN upload_time TYPE TIMESTAMPTZ USING upload_time::TIMESTAMPTZ,
+    ALTER COLUMN upload_time SET DEFAULT now();
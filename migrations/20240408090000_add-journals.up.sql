@@ -0,0 +1,24 @@
+-- Add up migration script here
+
+-- Changes of the index, the id of an entry is the serial used by mirrors.
+CREATE TABLE journals (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    version TEXT,
+    action TEXT NOT NULL,
+    submitted_date TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_journals_normalized_name ON journals (normalize_pep426_name(name));
+
+-- Files uploaded before the journal existed.
+INSERT INTO journals (name, version, action, submitted_date)
+SELECT
+    p.name,
+    r.version,
+    'add ' || COALESCE(NULLIF(rf.python_version, ''), 'source') || ' file ' || rf.filename,
+    COALESCE(rf.upload_time, now())
+FROM release_files rf
+JOIN releases r ON r.id = rf.release_id
+JOIN projects p ON p.id = r.project_id
+ORDER BY rf.id;
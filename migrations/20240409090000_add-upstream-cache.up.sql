@@ -0,0 +1,6 @@
+-- Add up migration script here
+
+-- Files cached from the upstream index keep the URL they were fetched from,
+-- uploaded files have none.
+ALTER TABLE release_files
+    ADD COLUMN upstream_url TEXT;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+ALTER TABLE projects
+    DROP COLUMN upstream_policy;
+
+DROP TYPE upstream_policy;
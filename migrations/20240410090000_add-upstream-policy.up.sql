@@ -0,0 +1,25 @@
+-- Add up migration script here
+
+-- Where the files of a project come from, so that a project of the upstream
+-- index never shadows an internal one.
+CREATE TYPE upstream_policy AS ENUM ('internal', 'upstream', 'merged');
+
+ALTER TABLE projects
+    ADD COLUMN upstream_policy upstream_policy NOT NULL DEFAULT 'internal';
+
+-- Projects only made of cached files were created by the upstream index.
+UPDATE projects p
+SET upstream_policy = 'upstream'
+WHERE EXISTS (
+    SELECT 1
+    FROM releases r
+    JOIN release_files rf ON r.id = rf.release_id
+    WHERE r.project_id = p.id
+)
+AND NOT EXISTS (
+    SELECT 1
+    FROM releases r
+    JOIN release_files rf ON r.id = rf.release_id
+    WHERE r.project_id = p.id
+        AND rf.upstream_url IS NULL
+);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+-- Serial of the upstream index each mirrored project was last synchronised
+-- at, an interrupted synchronisation resumes from the projects left behind.
+CREATE TABLE mirror_checkpoints (
+    normalized_name TEXT PRIMARY KEY,
+    last_serial BIGINT,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
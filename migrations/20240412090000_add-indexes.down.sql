@@ -0,0 +1,12 @@
+-- Add down migration script here
+
+ALTER TABLE projects
+    DROP CONSTRAINT unique_index_project,
+    DROP COLUMN index_id,
+    ADD CONSTRAINT projects_name_key UNIQUE (name),
+    ADD CONSTRAINT projects_normalized_name_key UNIQUE (normalized_name);
+
+DROP TABLE index_overrides;
+DROP TYPE index_rule;
+DROP TABLE index_parents;
+DROP TABLE indexes;
@@ -0,0 +1,39 @@
+-- Add up migration script here
+
+-- Named indexes, each one has its own projects and sees the projects of its
+-- parents. The root index holds the projects created so far, being the first
+-- index it gets the id 1.
+CREATE TABLE indexes (
+    id SERIAL PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO indexes (name) VALUES ('root');
+
+-- Parents are looked at in priority order, lowest first.
+CREATE TABLE index_parents (
+    index_id INT NOT NULL REFERENCES indexes(id) ON DELETE CASCADE,
+    parent_id INT NOT NULL REFERENCES indexes(id) ON DELETE CASCADE,
+    priority INT NOT NULL,
+    PRIMARY KEY (index_id, parent_id)
+);
+
+-- Per-project exceptions to the inheritance of an index.
+CREATE TYPE index_rule AS ENUM ('pin', 'merge', 'block');
+
+CREATE TABLE index_overrides (
+    index_id INT NOT NULL REFERENCES indexes(id) ON DELETE CASCADE,
+    normalized_name TEXT NOT NULL,
+    rule index_rule NOT NULL,
+    pinned_index_id INT REFERENCES indexes(id) ON DELETE CASCADE,
+    PRIMARY KEY (index_id, normalized_name),
+    CHECK ((rule = 'pin') = (pinned_index_id IS NOT NULL))
+);
+
+-- Project names are unique within an index, files stay unique across indexes.
+ALTER TABLE projects
+    ADD COLUMN index_id INT NOT NULL DEFAULT 1 REFERENCES indexes(id),
+    DROP CONSTRAINT projects_name_key,
+    DROP CONSTRAINT projects_normalized_name_key,
+    ADD CONSTRAINT unique_index_project UNIQUE (index_id, normalized_name);
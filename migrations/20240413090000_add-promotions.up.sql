@@ -0,0 +1,17 @@
+-- Add up migration script here
+
+-- Releases moved from an index to another one, typically from a staging index
+-- to its production parent. The files are moved, not copied, so they keep
+-- their digests.
+CREATE TABLE promotions (
+    id SERIAL PRIMARY KEY,
+    project TEXT NOT NULL,
+    version TEXT NOT NULL,
+    source_index_id INT NOT NULL REFERENCES indexes(id) ON DELETE CASCADE,
+    target_index_id INT NOT NULL REFERENCES indexes(id) ON DELETE CASCADE,
+    filenames TEXT[] NOT NULL,
+    promoted_by TEXT NOT NULL,
+    promoted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX idx_promotions_project ON promotions (normalize_pep426_name(project));
@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+DROP TABLE project_roles;
+DROP TYPE project_role;
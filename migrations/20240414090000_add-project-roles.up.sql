@@ -0,0 +1,17 @@
+-- Add up migration script here
+
+-- Accounts allowed to upload to a project. The owner also manages the
+-- maintainers. Projects created before ownership have no owner, their
+-- uploaders aren't known, an admin transfers them to their owner.
+CREATE TYPE project_role AS ENUM ('owner', 'maintainer');
+
+CREATE TABLE project_roles (
+    project_id INT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role project_role NOT NULL,
+    added TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (project_id, user_id)
+);
+
+CREATE UNIQUE INDEX idx_project_roles_owner ON project_roles (project_id) WHERE role = 'owner';
+CREATE INDEX idx_project_roles_user ON project_roles (user_id);
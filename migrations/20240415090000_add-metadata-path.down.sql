@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+ALTER TABLE release_files
+    DROP COLUMN metadata_path;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+
+-- PEP 658 - The core metadata is read from its own path, like the
+-- distribution it belongs to.
+ALTER TABLE release_files
+    ADD COLUMN metadata_path TEXT;
+
+UPDATE release_files
+SET metadata_path = path || '.metadata'
+WHERE metadata_file_sha256_digest IS NOT NULL;
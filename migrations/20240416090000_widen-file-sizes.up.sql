@@ -0,0 +1,5 @@
+-- Add up migration script here
+
+-- Wheels of 2 GiB and more don't fit in an INT.
+ALTER TABLE release_files
+    ALTER COLUMN size TYPE BIGINT;
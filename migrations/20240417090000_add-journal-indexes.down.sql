@@ -0,0 +1,6 @@
+-- Add down migration script here
+
+DROP INDEX idx_journals_index_name;
+CREATE INDEX idx_journals_normalized_name ON journals (normalize_pep426_name(name));
+
+ALTER TABLE journals DROP COLUMN index_id;
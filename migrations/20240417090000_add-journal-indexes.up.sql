@@ -0,0 +1,23 @@
+-- Add up migration script here
+
+-- Each index journals its own changes, the XML-RPC and JSON APIs only serve
+-- the journal of the root index.
+ALTER TABLE journals
+    ADD COLUMN index_id INT NOT NULL DEFAULT 1 REFERENCES indexes(id);
+
+-- Entries of projects the root index doesn't have were written by the named
+-- index holding the project.
+UPDATE journals j
+SET index_id = p.index_id
+FROM (
+    SELECT normalized_name, min(index_id) AS index_id
+    FROM projects
+    GROUP BY normalized_name
+    HAVING bool_and(index_id <> 1)
+) p
+WHERE normalize_pep426_name(j.name) = p.normalized_name;
+
+ALTER TABLE journals ALTER COLUMN index_id DROP DEFAULT;
+
+DROP INDEX idx_journals_normalized_name;
+CREATE INDEX idx_journals_index_name ON journals (index_id, normalize_pep426_name(name));
@@ -0,0 +1,8 @@
+-- Add up migration script here
+
+-- One-off data fixes done by the application, recorded so that they only
+-- run once.
+CREATE TABLE data_migrations (
+    name TEXT PRIMARY KEY,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,16 @@
+-- Add down migration script here
+
+DROP INDEX idx_release_files_sha256_digest;
+DROP INDEX idx_release_files_filename;
+
+DELETE FROM release_files a
+USING release_files b
+WHERE a.filename = b.filename AND a.id > b.id;
+
+ALTER TABLE release_files
+    DROP CONSTRAINT unique_release_file,
+    ADD CONSTRAINT release_files_filename_key UNIQUE (filename),
+    ADD CONSTRAINT release_files_path_key UNIQUE (path),
+    ADD CONSTRAINT release_files_md5_digest_key UNIQUE (md5_digest),
+    ADD CONSTRAINT release_files_sha256_digest_key UNIQUE (sha256_digest),
+    ADD CONSTRAINT release_files_blake2_256_digest_key UNIQUE (blake2_256_digest);
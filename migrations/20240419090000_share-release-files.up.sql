@@ -0,0 +1,14 @@
+-- Add up migration script here
+
+-- Promoted releases copy their file rows, the copies share the stored
+-- objects. Filenames stay unique within a release.
+ALTER TABLE release_files
+    DROP CONSTRAINT release_files_filename_key,
+    DROP CONSTRAINT release_files_path_key,
+    DROP CONSTRAINT release_files_md5_digest_key,
+    DROP CONSTRAINT release_files_sha256_digest_key,
+    DROP CONSTRAINT release_files_blake2_256_digest_key,
+    ADD CONSTRAINT unique_release_file UNIQUE (release_id, filename);
+
+CREATE INDEX idx_release_files_filename ON release_files (filename);
+CREATE INDEX idx_release_files_sha256_digest ON release_files (sha256_digest);
@@ -0,0 +1,32 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+
+use crate::simple::{
+    index::IndexDefinition,
+    simple_api::{PackageError, SimpleState},
+};
+
+#[tracing::instrument(name = "Api::Get index", skip(store))]
+pub async fn get_index(
+    State(store): State<SimpleState>,
+    Path(index): Path<String>,
+) -> Result<Json<IndexDefinition>, PackageError> {
+    Ok(Json(store.get_index_definition(&index).await?))
+}
+
+// Creates the index, or replaces the parents and the rules of an existing one.
+#[tracing::instrument(name = "Api::Set index", skip(store, definition))]
+pub async fn set_index(
+    State(store): State<SimpleState>,
+    Path(index): Path<String>,
+    Json(definition): Json<IndexDefinition>,
+) -> Response {
+    match store.set_index_definition(&index, &definition).await {
+        Ok(_) => (StatusCode::OK, "OK").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Extension, Path},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    authentication::User,
+    simple::{
+        index::Index,
+        simple_api::{Maintainer, PackageError},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct ProjectPath {
+    pub project: String,
+}
+
+#[derive(Deserialize)]
+pub struct MaintainerPath {
+    pub project: String,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct MaintainerRequest {
+    pub username: String,
+}
+
+fn maintainer_response(result: Result<(), PackageError>) -> Response {
+    match result {
+        Ok(_) => (StatusCode::OK, "OK").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Api::Get maintainers", skip(index, path))]
+pub async fn get_maintainers(
+    index: Index,
+    Path(path): Path<ProjectPath>,
+) -> Result<Json<Vec<Maintainer>>, PackageError> {
+    Ok(Json(index.store.get_maintainers(&path.project).await?))
+}
+
+#[tracing::instrument(name = "Api::Add maintainer", skip(index, path, user, body))]
+pub async fn add_maintainer(
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Extension(user): Extension<User>,
+    Json(body): Json<MaintainerRequest>,
+) -> Response {
+    maintainer_response(
+        index
+            .store
+            .add_maintainer(&path.project, &body.username, &user)
+            .await,
+    )
+}
+
+#[tracing::instrument(name = "Api::Remove maintainer", skip(index, path, user))]
+pub async fn remove_maintainer(
+    index: Index,
+    Path(path): Path<MaintainerPath>,
+    Extension(user): Extension<User>,
+) -> Response {
+    maintainer_response(
+        index
+            .store
+            .remove_maintainer(&path.project, &path.username, &user)
+            .await,
+    )
+}
+
+// The given user becomes the owner, the previous owner stays a maintainer.
+#[tracing::instrument(name = "Api::Transfer ownership", skip(index, path, user, body))]
+pub async fn transfer_ownership(
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Extension(user): Extension<User>,
+    Json(body): Json<MaintainerRequest>,
+) -> Response {
+    maintainer_response(
+        index
+            .store
+            .transfer_ownership(&path.project, &body.username, &user)
+            .await,
+    )
+}
@@ -0,0 +1,80 @@
+use axum::{
+    middleware::from_fn,
+    routing::{delete, get, post, put},
+    Router,
+};
+
+use crate::{
+    authentication::{admin_required, auth},
+    state::AppState,
+};
+
+mod indexes;
+mod maintainers;
+mod promotions;
+mod users;
+mod yank;
+
+// Routes before the `admin_required` layer are only open to admins, every
+// route requires an account.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users", post(users::create_user))
+        .route("/users/:username/role", put(users::set_role))
+        .route("/indexes/:index", put(indexes::set_index))
+        .route_layer(from_fn(admin_required))
+        .route(
+            "/projects/:project/releases/:version/yank",
+            post(yank::yank_release),
+        )
+        .route(
+            "/projects/:project/releases/:version/unyank",
+            post(yank::unyank_release),
+        )
+        .route(
+            "/projects/:project/files/:filename/yank",
+            post(yank::yank_file),
+        )
+        .route(
+            "/projects/:project/files/:filename/unyank",
+            post(yank::unyank_file),
+        )
+        .route("/indexes/:index", get(indexes::get_index))
+        .route(
+            "/indexes/:index/projects/:project/releases/:version/promote",
+            post(promotions::promote_release),
+        )
+        .route(
+            "/indexes/:index/projects/:project/promotions",
+            get(promotions::get_promotions),
+        )
+        .route(
+            "/indexes/:index/projects/:project/releases/:version/yank",
+            post(yank::yank_release),
+        )
+        .route(
+            "/indexes/:index/projects/:project/releases/:version/unyank",
+            post(yank::unyank_release),
+        )
+        .route(
+            "/indexes/:index/projects/:project/files/:filename/yank",
+            post(yank::yank_file),
+        )
+        .route(
+            "/indexes/:index/projects/:project/files/:filename/unyank",
+            post(yank::unyank_file),
+        )
+        .route(
+            "/indexes/:index/projects/:project/maintainers",
+            get(maintainers::get_maintainers).post(maintainers::add_maintainer),
+        )
+        .route(
+            "/indexes/:index/projects/:project/maintainers/:username",
+            delete(maintainers::remove_maintainer),
+        )
+        .route(
+            "/indexes/:index/projects/:project/owner",
+            put(maintainers::transfer_ownership),
+        )
+        .route_layer(from_fn(auth))
+}
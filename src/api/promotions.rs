@@ -0,0 +1,50 @@
+use axum::{extract::Path, Extension, Json};
+use serde::Deserialize;
+
+use crate::{
+    authentication::User,
+    simple::{
+        index::Index,
+        simple_api::{PackageError, Promotion},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct ProjectPath {
+    pub project: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReleasePath {
+    pub project: String,
+    pub version: String,
+}
+
+#[derive(Deserialize)]
+pub struct PromoteRequest {
+    pub target: String,
+}
+
+// The release of the index in the path is copied to the target index.
+#[tracing::instrument(name = "Api::Promote release", skip(index, path, user, body))]
+pub async fn promote_release(
+    index: Index,
+    Path(path): Path<ReleasePath>,
+    Extension(user): Extension<User>,
+    Json(body): Json<PromoteRequest>,
+) -> Result<Json<Promotion>, PackageError> {
+    let promotion = index
+        .store
+        .promote_release(&path.project, &path.version, &body.target, &user)
+        .await?;
+
+    Ok(Json(promotion))
+}
+
+#[tracing::instrument(name = "Api::Get promotions", skip(index, path))]
+pub async fn get_promotions(
+    index: Index,
+    Path(path): Path<ProjectPath>,
+) -> Result<Json<Vec<Promotion>>, PackageError> {
+    Ok(Json(index.store.get_promotions(&path.project).await?))
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Extension, Path},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::authentication::{self, Role};
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+#[derive(Deserialize)]
+pub struct RoleRequest {
+    pub role: Role,
+}
+
+#[tracing::instrument(name = "Api::Create user", skip(pool, user), fields(username = %user.username))]
+pub async fn create_user(
+    Extension(pool): Extension<PgPool>,
+    Json(user): Json<NewUser>,
+) -> Response {
+    match authentication::create_user(&pool, &user.username, user.password, user.role).await {
+        Ok(_) => (StatusCode::CREATED, "Created").into_response(),
+        Err(e) => match e.as_database_error().map(|e| e.kind()) {
+            Some(sqlx::error::ErrorKind::UniqueViolation) => (
+                StatusCode::CONFLICT,
+                format!("User {} already exists.", user.username),
+            )
+                .into_response(),
+            Some(sqlx::error::ErrorKind::CheckViolation) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid username {}.", user.username),
+            )
+                .into_response(),
+            _ => {
+                tracing::error!("Failed to create user: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error.").into_response()
+            }
+        },
+    }
+}
+
+#[tracing::instrument(name = "Api::Set user role", skip(pool, body))]
+pub async fn set_role(
+    Extension(pool): Extension<PgPool>,
+    Path(username): Path<String>,
+    Json(body): Json<RoleRequest>,
+) -> Response {
+    match authentication::set_role(&pool, &username, body.role).await {
+        Ok(true) => (StatusCode::OK, "OK").into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Not found.").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to set user role: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error.").into_response()
+        }
+    }
+}
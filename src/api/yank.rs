@@ -0,0 +1,110 @@
+use axum::{
+    extract::{Extension, Path},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    authentication::User,
+    simple::{index::Index, simple_api::PackageError},
+};
+
+#[derive(Deserialize)]
+pub struct ReleasePath {
+    pub project: String,
+    pub version: String,
+}
+
+#[derive(Deserialize)]
+pub struct FilePath {
+    pub project: String,
+    pub filename: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct YankRequest {
+    pub reason: Option<String>,
+}
+
+fn yank_response(result: Result<(), PackageError>) -> Response {
+    match result {
+        Ok(_) => (StatusCode::OK, "OK").into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Api::Yank release", skip(index, path, user, body))]
+pub async fn yank_release(
+    index: Index,
+    Path(path): Path<ReleasePath>,
+    Extension(user): Extension<User>,
+    body: Option<Json<YankRequest>>,
+) -> Response {
+    let Json(body) = body.unwrap_or_default();
+
+    yank_response(
+        index
+            .store
+            .yank_release(
+                &path.project,
+                &path.version,
+                true,
+                body.reason.as_deref(),
+                &user,
+            )
+            .await,
+    )
+}
+
+#[tracing::instrument(name = "Api::Unyank release", skip(index, path, user))]
+pub async fn unyank_release(
+    index: Index,
+    Path(path): Path<ReleasePath>,
+    Extension(user): Extension<User>,
+) -> Response {
+    yank_response(
+        index
+            .store
+            .yank_release(&path.project, &path.version, false, None, &user)
+            .await,
+    )
+}
+
+#[tracing::instrument(name = "Api::Yank file", skip(index, path, user, body))]
+pub async fn yank_file(
+    index: Index,
+    Path(path): Path<FilePath>,
+    Extension(user): Extension<User>,
+    body: Option<Json<YankRequest>>,
+) -> Response {
+    let Json(body) = body.unwrap_or_default();
+
+    yank_response(
+        index
+            .store
+            .yank_file(
+                &path.project,
+                &path.filename,
+                true,
+                body.reason.as_deref(),
+                &user,
+            )
+            .await,
+    )
+}
+
+#[tracing::instrument(name = "Api::Unyank file", skip(index, path, user))]
+pub async fn unyank_file(
+    index: Index,
+    Path(path): Path<FilePath>,
+    Extension(user): Extension<User>,
+) -> Response {
+    yank_response(
+        index
+            .store
+            .yank_file(&path.project, &path.filename, false, None, &user)
+            .await,
+    )
+}
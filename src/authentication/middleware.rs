@@ -6,7 +6,7 @@ use axum_extra::headers::Authorization;
 use axum_extra::TypedHeader;
 use hyper::{Request, StatusCode};
 
-use super::users::{AuthSession, Credentials};
+use super::users::{AuthSession, Credentials, User};
 
 pub async fn auth(
     auth_session: AuthSession,
@@ -30,3 +30,24 @@ pub async fn auth(
         Err(StatusCode::UNAUTHORIZED)
     }
 }
+
+// Only lets admins through. Goes after `auth` on the API, which puts the user
+// in the request, and after `login_required!` on the pages, where the user
+// comes from the session.
+pub async fn admin_required(
+    auth_session: AuthSession,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let user = req
+        .extensions()
+        .get::<User>()
+        .cloned()
+        .or(auth_session.user);
+
+    match user {
+        Some(user) if user.is_admin() => Ok(next.run(req).await),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
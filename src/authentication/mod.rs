@@ -2,6 +2,6 @@ mod middleware;
 mod sessions;
 mod users;
 
-pub use middleware::auth;
+pub use middleware::{admin_required, auth};
 pub use sessions::*;
 pub use users::*;
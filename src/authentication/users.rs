@@ -1,15 +1,33 @@
 use axum::async_trait;
-use password_auth::verify_password;
+use password_auth::{generate_hash, verify_password};
 use serde::{Deserialize, Serialize};
 
 use axum_login::{AuthUser, AuthnBackend, UserId};
 use sqlx::{FromRow, PgPool};
 
+// Contributors upload and manage releases, admins also manage the users and
+// the settings of the indexes and of the projects.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "user_roles", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    #[default]
+    Contributor,
+}
+
 #[derive(Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     id: uuid::Uuid,
     pub username: String,
     password: String,
+    pub role: Role,
+}
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
 }
 
 impl std::fmt::Debug for User {
@@ -17,6 +35,7 @@ impl std::fmt::Debug for User {
         f.debug_struct("User")
             .field("id", &self.id)
             .field("username", &self.username)
+            .field("role", &self.role)
             .field("password", &"[redacted]")
             .finish()
     }
@@ -72,7 +91,7 @@ impl AuthnBackend for Backend {
     ) -> Result<Option<Self::User>, Self::Error> {
         let user = sqlx::query_as!(
             Self::User,
-            r#"SELECT id, username, password_hash as password FROM users WHERE username=$1"#,
+            r#"SELECT id, username, password_hash as password, role as "role: Role" FROM users WHERE username=$1"#,
             creds.username
         )
         .fetch_optional(&self.db)
@@ -89,7 +108,7 @@ impl AuthnBackend for Backend {
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
         let user = sqlx::query_as!(
             Self::User,
-            r#"SELECT id, username, password_hash as password FROM users WHERE id = $1"#,
+            r#"SELECT id, username, password_hash as password, role as "role: Role" FROM users WHERE id = $1"#,
             user_id
         )
         .fetch_optional(&self.db)
@@ -100,3 +119,80 @@ impl AuthnBackend for Backend {
 }
 
 pub type AuthSession = axum_login::AuthSession<Backend>;
+
+// Account listed to the admins, without its password hash.
+#[derive(Serialize)]
+pub struct Account {
+    pub username: String,
+    pub role: Role,
+}
+
+pub async fn create_user(
+    db: &PgPool,
+    username: &str,
+    password: String,
+    role: Role,
+) -> Result<(), sqlx::Error> {
+    let password_hash = generate_hash(password);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO users (username, password_hash, role)
+        VALUES ($1::TEXT::CITEXT, $2, $3)
+        "#,
+        username,
+        password_hash,
+        role as Role,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+// Returns false when the user doesn't exist.
+pub async fn set_role(db: &PgPool, username: &str, role: Role) -> Result<bool, sqlx::Error> {
+    let updated = sqlx::query!(
+        r#"UPDATE users SET role = $2 WHERE username = $1"#,
+        username,
+        role as Role,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(updated.rows_affected() > 0)
+}
+
+pub async fn get_accounts(db: &PgPool) -> Result<Vec<Account>, sqlx::Error> {
+    sqlx::query_as!(
+        Account,
+        r#"SELECT username, role as "role: Role" FROM users ORDER BY username"#
+    )
+    .fetch_all(db)
+    .await
+}
+
+// Gives the admin role to the account set in the configuration, so the first
+// admin can sign in. The account is created with the password when it doesn't
+// exist yet, an existing account keeps its password.
+pub async fn bootstrap_admin(
+    db: &PgPool,
+    username: &str,
+    password: &str,
+) -> Result<(), sqlx::Error> {
+    let password_hash = generate_hash(password);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO users (username, password_hash, role)
+        VALUES ($1::TEXT::CITEXT, $2, 'admin')
+        ON CONFLICT (username) DO UPDATE SET role = 'admin'
+        "#,
+        username,
+        password_hash,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
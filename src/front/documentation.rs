@@ -8,9 +8,13 @@ use axum_template::RenderHtml;
 use minijinja::context;
 use serde::Deserialize;
 use sqlx::PgPool;
-use crate::simple::package::CoreMetadata;
 
-use crate::{engine::AppEngine, state::AppState};
+use crate::{
+    authentication::AuthSession,
+    engine::AppEngine,
+    simple::{index::ROOT_INDEX_ID, version::latest_version},
+    state::AppState,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new().route("/:project/:version", get(documentation))
@@ -19,27 +23,35 @@ pub fn router() -> Router<AppState> {
 async fn get_version(pool: &PgPool, project: &str, version: &str) -> String {
     match version {
         v if v.eq("latest") => {
-            let latest_version = sqlx::query!(
+            let releases = sqlx::query!(
                 r#"
                 WITH selected_project AS (
                     SELECT id
                     FROM projects
                     WHERE normalized_name = normalize_pep426_name($1)
+                        AND index_id = $2
                 )
-                SELECT r.version AS version
+                SELECT r.version AS version, r.yanked AS yanked
                 FROM selected_project sp
                     JOIN releases r
                         ON sp.id = r.project_id
-                ORDER BY r.version DESC
-                LIMIT 1
                 "#,
-                project
+                project,
+                ROOT_INDEX_ID
             )
-            .fetch_one(pool)
+            .fetch_all(pool)
             .await
-            .expect("Unable to get latest version");
+            .expect("Unable to get project versions");
+
+            // Versions are ordered following PEP 440, yanked releases are only
+            // used when every release of the project was yanked.
+            let available = releases.iter().filter(|r| !r.yanked).map(|r| r.version.as_str());
+            let all = releases.iter().map(|r| r.version.as_str());
 
-            latest_version.version
+            latest_version(available)
+                .or_else(|| latest_version(all))
+                .expect("Unable to get latest version")
+                .to_string()
         }
         v => v.to_string(),
     }
@@ -54,6 +66,7 @@ async fn documentation_content(pool: &PgPool, project: &str, version: &str) -> S
             SELECT id
             FROM projects
             WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $3
         )
         SELECT rd.html AS html
         FROM selected_project sp
@@ -65,12 +78,16 @@ async fn documentation_content(pool: &PgPool, project: &str, version: &str) -> S
             LIMIT 1
         "#,
         project,
-        version
+        version,
+        ROOT_INDEX_ID
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await
     .expect("Unable to fetch html content")
-    .html
+    // Releases uploaded without a description, or cached from the upstream
+    // index, have none.
+    .map(|description| description.html)
+    .unwrap_or_default()
 }
 
 #[derive(Deserialize)]
@@ -87,7 +104,6 @@ impl ReleaseInfo {
     pub fn keywords_list(&self) -> Vec<String> {
         if let Some(keywords) = &self.keywords {
             keywords.split(",")
-                .into_iter()
                 .map(|kw| kw.to_string())
                 .collect()
         } else {
@@ -104,6 +120,7 @@ async fn package_meta(pool: &PgPool, project: &str, version: &str) -> ReleaseInf
             select p.id as project_id
             from projects p
             where p.normalized_name = normalize_pep426_name($1)
+                and p.index_id = $3
         )
         SELECT
             r.version AS version,
@@ -116,7 +133,7 @@ async fn package_meta(pool: &PgPool, project: &str, version: &str) -> ReleaseInf
         JOIN selected_project sp
         ON sp.project_id = r.project_id
         WHERE r.version = $2"#,
-        project, version)
+        project, version, ROOT_INDEX_ID)
             .fetch_one(pool)
             .await
             .unwrap()
@@ -124,6 +141,7 @@ async fn package_meta(pool: &PgPool, project: &str, version: &str) -> ReleaseInf
 
 pub async fn documentation(
     engine: AppEngine,
+    auth_session: AuthSession,
     Extension(pool): Extension<PgPool>,
     Path((project, version)): Path<(String, String)>,
 ) -> impl IntoResponse {
@@ -134,11 +152,16 @@ pub async fn documentation(
         "documentation.jinja",
         engine,
         context! {
+            is_authenticated => auth_session.user.is_some(),
+            is_admin => auth_session.user.is_some_and(|user| user.is_admin()),
             package_name => project,
             content => doc,
             keywords => info.keywords_list(),
-            home_page => info.home_page,
-            author => info.author
+            version => info.version,
+            homepage => info.home_page,
+            author => info.author,
+            author_email => info.author_email,
+            license => info.license
         },
     )
 }
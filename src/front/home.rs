@@ -5,11 +5,11 @@ use minijinja::context;
 use crate::{authentication::AuthSession, engine::AppEngine};
 
 pub async fn home(engine: AppEngine, auth_session: AuthSession) -> impl IntoResponse {
-    if let Some(_uid) = auth_session.user {
+    if let Some(user) = auth_session.user {
         RenderHtml(
             "home/home.jinja",
             engine,
-            context! { is_authenticated => true },
+            context! { is_authenticated => true, is_admin => user.is_admin() },
         )
     } else {
         RenderHtml(
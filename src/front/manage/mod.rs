@@ -1,4 +1,5 @@
 use axum::{
+    middleware::from_fn,
     response::Redirect,
     routing::{get, post},
     Router,
@@ -6,15 +7,32 @@ use axum::{
 use axum_login::login_required;
 
 use crate::{
-    authentication::{AuthSession, Backend},
+    authentication::{admin_required, AuthSession, Backend},
     state::AppState,
 };
 
+pub mod project;
 pub mod sign_in;
 pub mod sign_up;
+pub mod users;
 
+// Users and project settings are managed by the admins, the other pages are
+// open to every signed in user.
 pub fn router() -> Router<AppState> {
     Router::new()
+        .route("/users", get(users::users))
+        .route("/users/:username/role", post(users::set_role))
+        .route("/create_user", post(sign_up::create_user))
+        .route("/sign_up", get(sign_up::sign_up))
+        .route(
+            "/projects/:project/settings",
+            post(project::update_settings),
+        )
+        .route(
+            "/indexes/:index/projects/:project/settings",
+            post(project::update_settings),
+        )
+        .route_layer(from_fn(admin_required))
         .route(
             "/logout",
             get(|mut auth_session: AuthSession| async move {
@@ -25,9 +43,53 @@ pub fn router() -> Router<AppState> {
                 Redirect::to("/")
             }),
         )
+        .route("/projects/:project", get(project::project))
+        .route(
+            "/projects/:project/releases/:version/:action",
+            post(project::yank_release),
+        )
+        .route(
+            "/projects/:project/files/:filename/:action",
+            post(project::yank_file),
+        )
+        .route(
+            "/projects/:project/maintainers",
+            post(project::add_maintainer),
+        )
+        .route(
+            "/projects/:project/maintainers/:username/remove",
+            post(project::remove_maintainer),
+        )
+        .route("/projects/:project/owner", post(project::transfer_ownership))
+        .route(
+            "/indexes/:index/projects/:project",
+            get(project::project),
+        )
+        .route(
+            "/indexes/:index/projects/:project/releases/:version/:action",
+            post(project::yank_release),
+        )
+        .route(
+            "/indexes/:index/projects/:project/files/:filename/:action",
+            post(project::yank_file),
+        )
+        .route(
+            "/indexes/:index/projects/:project/promotions",
+            post(project::promote_release),
+        )
+        .route(
+            "/indexes/:index/projects/:project/maintainers",
+            post(project::add_maintainer),
+        )
+        .route(
+            "/indexes/:index/projects/:project/maintainers/:username/remove",
+            post(project::remove_maintainer),
+        )
+        .route(
+            "/indexes/:index/projects/:project/owner",
+            post(project::transfer_ownership),
+        )
         .route_layer(login_required!(Backend, login_url = "/manage/sign_in"))
         .route("/sign_in", get(sign_in::sign_in))
         .route("/login", post(sign_in::login))
-        .route("/create_user", post(sign_up::create_user))
-        .route("/sign_up", get(sign_up::sign_up))
 }
@@ -0,0 +1,370 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Form,
+};
+use axum_template::RenderHtml;
+use hyper::StatusCode;
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    authentication::AuthSession,
+    engine::AppEngine,
+    simple::{
+        index::{Index, ROOT_INDEX},
+        simple_api::{PackageError, PkgDist, ProjectRole, UpstreamPolicy},
+    },
+};
+
+// Path parameters are read by name, pages of the named indexes also capture
+// the index.
+#[derive(Debug, Deserialize)]
+pub struct ProjectPath {
+    pub project: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseActionPath {
+    pub project: String,
+    pub version: String,
+    pub action: YankAction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileActionPath {
+    pub project: String,
+    pub filename: String,
+    pub action: YankAction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum YankAction {
+    Yank,
+    Unyank,
+}
+
+#[derive(Deserialize)]
+pub struct YankForm {
+    pub reason: Option<String>,
+}
+
+// Unchecked checkboxes are not sent with the form.
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    pub allow_file_overwrite: Option<String>,
+    pub upstream_policy: Option<UpstreamPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintainerPath {
+    pub project: String,
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct MaintainerForm {
+    pub username: String,
+}
+
+#[derive(Deserialize)]
+pub struct PromoteForm {
+    pub version: String,
+    pub target: String,
+}
+
+// Projects of the root index keep their original URL.
+fn project_url(index: &str, project: &str) -> String {
+    match index {
+        ROOT_INDEX => format!("/manage/projects/{}", project),
+        index => format!("/manage/indexes/{}/projects/{}", index, project),
+    }
+}
+
+#[derive(Serialize)]
+struct ReleaseFiles {
+    version: String,
+    yanked: bool,
+    yanked_reason: Option<String>,
+    files: Vec<PkgDist>,
+}
+
+#[tracing::instrument(name = "Manage::Show project", skip(engine, auth_session, index, path))]
+pub async fn project(
+    engine: AppEngine,
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<ProjectPath>,
+) -> impl IntoResponse {
+    // The settings are only shown to the admins, who can change them.
+    let is_admin = auth_session
+        .user
+        .as_ref()
+        .is_some_and(|user| user.is_admin());
+    let username = auth_session
+        .user
+        .map(|user| user.username)
+        .unwrap_or_default();
+    let project = path.project;
+    let project_url = project_url(&index.name, &project);
+    let store = index.store;
+
+    // Unknown names can be claimed, uploads are then accepted even when the
+    // upstream index has a project with the same name. Only the root index
+    // looks at the upstream index.
+    let upstream_policy = match store.get_upstream_policy(&project).await {
+        Ok(policy) => policy,
+        Err(PackageError::NotFound) if index.name == ROOT_INDEX => {
+            return Ok(RenderHtml(
+                "manage/claim.jinja",
+                engine,
+                context! {
+                    is_authenticated => true,
+                    is_admin => is_admin,
+                    project => project,
+                    project_url => project_url,
+                },
+            ))
+        }
+        Err(PackageError::NotFound) => return Err((StatusCode::NOT_FOUND, "Unknown project")),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Unable to load project")),
+    };
+
+    // Releases are promoted to the parents of the index.
+    let (
+        Ok(releases),
+        Ok(mut dists),
+        Ok(allow_file_overwrite),
+        Ok(definition),
+        Ok(promotions),
+        Ok(maintainers),
+    ) = (
+        store.get_releases(&project).await,
+        store.get_dists(&project).await,
+        store.get_overwrite_policy(&project).await,
+        store.get_index_definition(&index.name).await,
+        store.get_promotions(&project).await,
+        store.get_maintainers(&project).await,
+    )
+    else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Unable to load project"));
+    };
+
+    // The owner and the admins manage the maintainers.
+    let is_owner = is_admin
+        || maintainers
+            .iter()
+            .any(|m| m.username == username && m.role == ProjectRole::Owner);
+
+    let releases: Vec<ReleaseFiles> = releases
+        .into_iter()
+        .map(|release| {
+            let (files, others) = dists.drain(..).partition(|d| d.version == release.version);
+            dists = others;
+
+            ReleaseFiles {
+                version: release.version,
+                yanked: release.yanked,
+                yanked_reason: release.yanked_reason,
+                files,
+            }
+        })
+        .collect();
+
+    Ok(RenderHtml(
+        "manage/project.jinja",
+        engine,
+        context! {
+            is_authenticated => true,
+            is_admin => is_admin,
+            index => index.name,
+            project => project,
+            project_url => project_url,
+            releases => releases,
+            allow_file_overwrite => allow_file_overwrite,
+            upstream_policy => upstream_policy,
+            promotion_targets => definition.parents,
+            promotions => promotions,
+            username => username,
+            maintainers => maintainers,
+            is_owner => is_owner,
+        },
+    ))
+}
+
+fn redirect_to_project(project_url: String, result: Result<(), PackageError>) -> Response {
+    match result {
+        Ok(_) => (StatusCode::OK, [("HX-Redirect", project_url)]).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[tracing::instrument(name = "Manage::Yank release", skip(auth_session, index, form))]
+pub async fn yank_release(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<ReleaseActionPath>,
+    Form(form): Form<YankForm>,
+) -> impl IntoResponse {
+    let ReleaseActionPath {
+        project,
+        version,
+        action,
+    } = path;
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let store = index.store;
+    let reason = form.reason.filter(|r| !r.trim().is_empty());
+
+    let result = match action {
+        YankAction::Yank => {
+            store
+                .yank_release(&project, &version, true, reason.as_deref(), &user)
+                .await
+        }
+        YankAction::Unyank => {
+            store
+                .yank_release(&project, &version, false, None, &user)
+                .await
+        }
+    };
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Yank file", skip(auth_session, index, form))]
+pub async fn yank_file(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<FileActionPath>,
+    Form(form): Form<YankForm>,
+) -> impl IntoResponse {
+    let FileActionPath {
+        project,
+        filename,
+        action,
+    } = path;
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let store = index.store;
+    let reason = form.reason.filter(|r| !r.trim().is_empty());
+
+    let result = match action {
+        YankAction::Yank => {
+            store
+                .yank_file(&project, &filename, true, reason.as_deref(), &user)
+                .await
+        }
+        YankAction::Unyank => {
+            store
+                .yank_file(&project, &filename, false, None, &user)
+                .await
+        }
+    };
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Update project settings", skip(index, form))]
+pub async fn update_settings(
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Form(form): Form<SettingsForm>,
+) -> impl IntoResponse {
+    let project = path.project;
+    let store = index.store;
+    let result = async {
+        if let Some(policy) = form.upstream_policy {
+            store.set_upstream_policy(&project, policy).await?;
+        }
+        store
+            .set_overwrite_policy(&project, form.allow_file_overwrite.is_some())
+            .await
+    }
+    .await;
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Promote release", skip(auth_session, index, form))]
+pub async fn promote_release(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Form(form): Form<PromoteForm>,
+) -> impl IntoResponse {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let project = path.project;
+
+    let result = index
+        .store
+        .promote_release(&project, &form.version, &form.target, &user)
+        .await
+        .map(|_| ());
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Add maintainer", skip(auth_session, index, form))]
+pub async fn add_maintainer(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Form(form): Form<MaintainerForm>,
+) -> impl IntoResponse {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let project = path.project;
+
+    let result = index
+        .store
+        .add_maintainer(&project, form.username.trim(), &user)
+        .await;
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Remove maintainer", skip(auth_session, index))]
+pub async fn remove_maintainer(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<MaintainerPath>,
+) -> impl IntoResponse {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let MaintainerPath { project, username } = path;
+
+    let result = index
+        .store
+        .remove_maintainer(&project, &username, &user)
+        .await;
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
+
+#[tracing::instrument(name = "Manage::Transfer ownership", skip(auth_session, index, form))]
+pub async fn transfer_ownership(
+    auth_session: AuthSession,
+    index: Index,
+    Path(path): Path<ProjectPath>,
+    Form(form): Form<MaintainerForm>,
+) -> impl IntoResponse {
+    let Some(user) = auth_session.user else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let project = path.project;
+
+    let result = index
+        .store
+        .transfer_ownership(&project, form.username.trim(), &user)
+        .await;
+
+    redirect_to_project(project_url(&index.name, &project), result)
+}
@@ -5,14 +5,18 @@ use axum_template::RenderHtml;
 use minijinja::context;
 use serde::Deserialize;
 
-use password_auth::generate_hash;
-
+use crate::authentication::{self, Role};
 use crate::engine::AppEngine;
 
 use sqlx::PgPool;
 
+// Accounts are created by the admins.
 pub async fn sign_up(engine: AppEngine) -> impl IntoResponse {
-    RenderHtml("sign_up/sign_up.jinja", engine, &())
+    RenderHtml(
+        "sign_up/sign_up.jinja",
+        engine,
+        context! { is_authenticated => true, is_admin => true },
+    )
 }
 
 #[derive(Deserialize)]
@@ -20,6 +24,8 @@ pub struct SignUp {
     username: String,
     password: String,
     confirm_password: String,
+    #[serde(default)]
+    role: Role,
 }
 
 #[tracing::instrument(name = "Manage::Create user", skip(engine, pool, form))]
@@ -47,19 +53,8 @@ pub async fn create_user(
         );
     }
 
-    let password = form.password;
-    let password_hash = generate_hash(password);
-
-    let user_created = sqlx::query!(
-        r#"
-        INSERT INTO users (username, password_hash)
-        VALUES ($1::TEXT::CITEXT, $2)
-        "#,
-        &form.username,
-        password_hash,
-    )
-    .execute(&pool)
-    .await;
+    let user_created =
+        authentication::create_user(&pool, &form.username, form.password, form.role).await;
 
     match user_created {
         Ok(_) => {
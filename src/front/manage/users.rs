@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Extension, Path},
+    response::IntoResponse,
+    Form,
+};
+use axum_template::RenderHtml;
+use hyper::StatusCode;
+use minijinja::context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::{self, AuthSession, Role},
+    engine::AppEngine,
+};
+
+#[derive(Deserialize)]
+pub struct RoleForm {
+    pub role: Role,
+}
+
+#[tracing::instrument(name = "Manage::Show users", skip(engine, auth_session, pool))]
+pub async fn users(
+    engine: AppEngine,
+    auth_session: AuthSession,
+    Extension(pool): Extension<PgPool>,
+) -> impl IntoResponse {
+    let Ok(accounts) = authentication::get_accounts(&pool).await else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Unable to load users"));
+    };
+    let current_user = auth_session
+        .user
+        .map(|user| user.username)
+        .unwrap_or_default();
+
+    Ok(RenderHtml(
+        "manage/users.jinja",
+        engine,
+        context! {
+            is_authenticated => true,
+            is_admin => true,
+            current_user => current_user,
+            accounts => accounts,
+        },
+    ))
+}
+
+#[tracing::instrument(name = "Manage::Set user role", skip(pool, form))]
+pub async fn set_role(
+    Extension(pool): Extension<PgPool>,
+    Path(username): Path<String>,
+    Form(form): Form<RoleForm>,
+) -> impl IntoResponse {
+    match authentication::set_role(&pool, &username, form.role).await {
+        Ok(true) => (StatusCode::OK, [("HX-Redirect", "/manage/users")]).into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Unknown user").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to set user role: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Unable to set the role").into_response()
+        }
+    }
+}
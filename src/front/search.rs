@@ -9,7 +9,7 @@ use minijinja::context;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
-use crate::engine::AppEngine;
+use crate::{engine::AppEngine, simple::index::ROOT_INDEX_ID};
 
 pub async fn show_documentation(Path(package): Path<String>) -> impl IntoResponse {
     (
@@ -44,8 +44,10 @@ pub async fn search_package(
             r#"
         SELECT name, has_docs FROM projects
         WHERE normalized_name LIKE (normalize_pep426_name($1) || '%')
+            AND index_id = $2
         "#,
-            query
+            query,
+            ROOT_INDEX_ID
         )
         .fetch_all(&pool)
         .await;
@@ -1,3 +1,4 @@
+pub mod api;
 pub mod front;
 pub mod greeting;
 
@@ -10,4 +11,5 @@ pub mod startup;
 pub mod telemetry;
 
 pub mod healthcheck;
+pub mod pypi;
 pub mod simple;
@@ -9,8 +9,10 @@ async fn main() -> hyper::Result<()> {
     let subscriber = get_subscriber("nest".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
+    // The configuration file can be given as first argument.
     tracing::debug!("Read configuration");
-    let configuration = settings::get_settings(None).expect("Failed to read configuration.");
+    let configuration = settings::get_settings(std::env::args().nth(1))
+        .expect("Failed to read configuration.");
 
     tracing::debug!("Build Application");
     let application = Application::build(configuration).await;
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use sqlx::PgPool;
+
+use super::models::{project_urls, DigestsJson, FileJson, InfoJson, ProjectJson};
+use super::queries::{
+    get_files, get_last_serial, get_project, get_releases, get_requires_dist, latest_release,
+    FileRow,
+};
+use crate::simple::{simple_api::PackageError, version::Version};
+
+fn file_json(file: &FileRow, project: &str) -> FileJson {
+    FileJson {
+        comment_text: String::new(),
+        digests: DigestsJson {
+            blake2b_256: file.blake2_256_digest.to_owned(),
+            md5: file.md5_digest.to_owned(),
+            sha256: file.sha256_digest.to_owned(),
+        },
+        downloads: -1,
+        filename: file.filename.to_owned(),
+        has_sig: false,
+        md5_digest: file.md5_digest.to_owned(),
+        packagetype: file.packagetype.to_owned(),
+        python_version: file.python_version.to_owned().unwrap_or_default(),
+        requires_python: file.requires_python.to_owned(),
+        size: file.size.unwrap_or_default(),
+        upload_time: file.upload_time.to_owned(),
+        upload_time_iso_8601: file.upload_time_iso_8601.to_owned(),
+        url: format!("/simple/{}/{}", project, file.filename),
+        yanked: file.yanked,
+        yanked_reason: file.yanked_reason.to_owned(),
+    }
+}
+
+pub(super) async fn project_json(
+    pool: &PgPool,
+    project: &str,
+    version: Option<&str>,
+) -> Result<ProjectJson, PackageError> {
+    let project = get_project(pool, project).await?;
+    let releases = get_releases(pool, project.id).await?;
+    let files = get_files(pool, project.id).await?;
+
+    let release = match version {
+        Some(version) => {
+            let canonical_version = version
+                .parse::<Version>()
+                .map(|v| v.canonical())
+                .unwrap_or_else(|_| version.to_string());
+
+            releases
+                .iter()
+                .find(|r| r.canonical_version == canonical_version)
+        }
+        None => latest_release(&releases),
+    }
+    .ok_or(PackageError::NotFound)?;
+
+    let requires_dist = get_requires_dist(pool, release.id).await?;
+
+    let urls = files
+        .iter()
+        .filter(|f| f.release_id == release.id)
+        .map(|f| file_json(f, &project.name))
+        .collect();
+
+    // Releases without files are listed too, like on PyPI.
+    let all_releases = version.is_none().then(|| {
+        let mut all_releases: BTreeMap<String, Vec<FileJson>> = releases
+            .iter()
+            .map(|r| (r.version.to_owned(), Vec::new()))
+            .collect();
+
+        for r in &releases {
+            let release_files = files
+                .iter()
+                .filter(|f| f.release_id == r.id)
+                .map(|f| file_json(f, &project.name));
+            all_releases
+                .entry(r.version.to_owned())
+                .or_default()
+                .extend(release_files);
+        }
+
+        all_releases
+    });
+
+    let last_serial = get_last_serial(pool, &project.name).await?;
+
+    let info = InfoJson {
+        author: release.author.to_owned(),
+        author_email: release.author_email.to_owned(),
+        classifiers: release.classifiers.to_owned(),
+        description: release.description.to_owned(),
+        description_content_type: release.description_content_type.to_owned(),
+        download_url: release.download_url.to_owned(),
+        home_page: release.home_page.to_owned(),
+        keywords: release.keywords.to_owned(),
+        license: release.license.to_owned(),
+        maintainer: release.maintainer.to_owned(),
+        maintainer_email: release.maintainer_email.to_owned(),
+        name: project.name.to_owned(),
+        package_url: format!("/packages/{}/latest", project.name),
+        platform: release.platform.to_owned(),
+        project_url: format!("/packages/{}/latest", project.name),
+        project_urls: project_urls(&release.project_urls),
+        provides_extra: release.provides_extras.to_owned(),
+        release_url: format!("/packages/{}/{}", project.name, release.version),
+        requires_dist: (!requires_dist.is_empty()).then_some(requires_dist),
+        requires_python: release.requires_python.to_owned(),
+        summary: release.summary.to_owned(),
+        version: release.version.to_owned(),
+        yanked: release.yanked,
+        yanked_reason: release.yanked_reason.to_owned(),
+    };
+
+    Ok(ProjectJson {
+        info,
+        last_serial,
+        releases: all_releases,
+        urls,
+        vulnerabilities: Vec::new(),
+    })
+}
+
+#[tracing::instrument(name = "Pypi::Project JSON", skip(pool))]
+pub async fn project(
+    Extension(pool): Extension<PgPool>,
+    Path(project): Path<String>,
+) -> Result<Json<ProjectJson>, PackageError> {
+    Ok(Json(project_json(&pool, &project, None).await?))
+}
+
+#[tracing::instrument(name = "Pypi::Release JSON", skip(pool))]
+pub async fn release(
+    Extension(pool): Extension<PgPool>,
+    Path((project, version)): Path<(String, String)>,
+) -> Result<Json<ProjectJson>, PackageError> {
+    Ok(Json(project_json(&pool, &project, Some(&version)).await?))
+}
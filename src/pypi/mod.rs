@@ -0,0 +1,19 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use crate::state::AppState;
+
+mod json;
+mod models;
+mod queries;
+mod xmlrpc;
+
+// PyPI legacy APIs, used by tooling which doesn't speak the simple API.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(xmlrpc::handler))
+        .route("/:project/json", get(json::project))
+        .route("/:project/:version/json", get(json::release))
+}
@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+// PyPI JSON API
+// https://warehouse.pypa.io/api-reference/json.html
+#[derive(Serialize)]
+pub struct ProjectJson {
+    pub info: InfoJson,
+    pub last_serial: i64,
+    // Only served by the project endpoint, not by the release endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub releases: Option<BTreeMap<String, Vec<FileJson>>>,
+    pub urls: Vec<FileJson>,
+    pub vulnerabilities: Vec<()>,
+}
+
+#[derive(Serialize)]
+pub struct InfoJson {
+    pub author: Option<String>,
+    pub author_email: Option<String>,
+    pub classifiers: Vec<String>,
+    pub description: Option<String>,
+    pub description_content_type: Option<String>,
+    pub download_url: Option<String>,
+    pub home_page: Option<String>,
+    pub keywords: Option<String>,
+    pub license: Option<String>,
+    pub maintainer: Option<String>,
+    pub maintainer_email: Option<String>,
+    pub name: String,
+    pub package_url: String,
+    pub platform: Option<String>,
+    pub project_url: String,
+    pub project_urls: Option<HashMap<String, String>>,
+    pub provides_extra: Vec<String>,
+    pub release_url: String,
+    pub requires_dist: Option<Vec<String>>,
+    pub requires_python: Option<String>,
+    pub summary: Option<String>,
+    pub version: String,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DigestsJson {
+    pub blake2b_256: String,
+    pub md5: String,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FileJson {
+    pub comment_text: String,
+    pub digests: DigestsJson,
+    pub downloads: i64,
+    pub filename: String,
+    pub has_sig: bool,
+    pub md5_digest: String,
+    pub packagetype: String,
+    pub python_version: String,
+    pub requires_python: Option<String>,
+    pub size: i64,
+    pub upload_time: String,
+    pub upload_time_iso_8601: String,
+    pub url: String,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+// `Project-URL` entries are written as `{label}, {url}`.
+pub fn project_urls(entries: &[String]) -> Option<HashMap<String, String>> {
+    let urls: HashMap<String, String> = entries
+        .iter()
+        .filter_map(|entry| entry.split_once(','))
+        .map(|(label, url)| (label.trim().to_string(), url.trim().to_string()))
+        .collect();
+
+    (!urls.is_empty()).then_some(urls)
+}
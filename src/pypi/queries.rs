@@ -0,0 +1,272 @@
+use sqlx::PgPool;
+
+use crate::simple::{index::ROOT_INDEX_ID, simple_api::PackageError, version::latest_version};
+
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+}
+
+pub struct ReleaseRow {
+    pub id: i32,
+    pub version: String,
+    pub canonical_version: String,
+    pub author: Option<String>,
+    pub author_email: Option<String>,
+    pub maintainer: Option<String>,
+    pub maintainer_email: Option<String>,
+    pub home_page: Option<String>,
+    pub license: Option<String>,
+    pub summary: Option<String>,
+    pub keywords: Option<String>,
+    pub platform: Option<String>,
+    pub download_url: Option<String>,
+    pub requires_python: Option<String>,
+    pub classifiers: Vec<String>,
+    pub project_urls: Vec<String>,
+    pub provides_extras: Vec<String>,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+    pub description: Option<String>,
+    pub description_content_type: Option<String>,
+}
+
+pub struct FileRow {
+    pub release_id: i32,
+    pub filename: String,
+    pub packagetype: String,
+    pub python_version: Option<String>,
+    pub requires_python: Option<String>,
+    pub size: Option<i64>,
+    pub md5_digest: String,
+    pub sha256_digest: String,
+    pub blake2_256_digest: String,
+    pub upload_time: String,
+    pub upload_time_iso_8601: String,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+pub async fn get_project(pool: &PgPool, project: &str) -> Result<Project, PackageError> {
+    sqlx::query_as!(
+        Project,
+        r#"
+        SELECT id, name
+        FROM projects
+        WHERE normalized_name = normalize_pep426_name($1)
+            AND index_id = $2
+        "#,
+        project,
+        ROOT_INDEX_ID
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(PackageError::NotFound)
+}
+
+pub async fn get_releases(pool: &PgPool, project_id: i32) -> Result<Vec<ReleaseRow>, PackageError> {
+    let releases = sqlx::query_as!(
+        ReleaseRow,
+        r#"
+        SELECT
+            r.id,
+            r.version,
+            r.canonical_version,
+            NULLIF(r.author, '') as author,
+            NULLIF(r.author_email, '') as author_email,
+            NULLIF(r.maintainer, '') as maintainer,
+            NULLIF(r.maintainer_email, '') as maintainer_email,
+            NULLIF(r.home_page, '') as home_page,
+            NULLIF(r.license, '') as license,
+            NULLIF(r.summary, '') as summary,
+            NULLIF(r.keywords, '') as keywords,
+            NULLIF(r.platform, '') as platform,
+            NULLIF(r.download_url, '') as download_url,
+            NULLIF(r.requires_python, '') as requires_python,
+            r.classifiers,
+            r.project_urls,
+            r.provides_extras,
+            r.yanked,
+            r.yanked_reason,
+            rd.raw as "description?",
+            rd.content_type as "description_content_type?"
+        FROM releases r
+        LEFT JOIN release_descriptions rd ON rd.release_id = r.id
+        WHERE r.project_id = $1
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(releases)
+}
+
+pub async fn get_files(pool: &PgPool, project_id: i32) -> Result<Vec<FileRow>, PackageError> {
+    let files = sqlx::query_as!(
+        FileRow,
+        r#"
+        SELECT
+            r.id as release_id,
+            rf.filename,
+            rf.packagetype::TEXT as "packagetype!",
+            NULLIF(rf.python_version, '') as python_version,
+            NULLIF(rf.requires_python, '') as requires_python,
+            rf.size,
+            rf.md5_digest,
+            rf.sha256_digest::TEXT as "sha256_digest!",
+            rf.blake2_256_digest::TEXT as "blake2_256_digest!",
+            to_char(rf.upload_time AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS') as "upload_time!",
+            to_char(rf.upload_time AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"') as "upload_time_iso_8601!",
+            (rf.yanked OR r.yanked) as "yanked!",
+            COALESCE(rf.yanked_reason, r.yanked_reason) as yanked_reason
+        FROM releases r
+        JOIN release_files rf ON rf.release_id = r.id
+        WHERE r.project_id = $1
+        ORDER BY rf.filename ASC
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(files)
+}
+
+pub async fn get_requires_dist(
+    pool: &PgPool,
+    release_id: i32,
+) -> Result<Vec<String>, PackageError> {
+    let requirements = sqlx::query!(
+        r#"
+        SELECT specifier as "specifier!"
+        FROM release_dependencies
+        WHERE release_id = $1
+            AND kind = 'requires_dist'
+            AND specifier IS NOT NULL
+        ORDER BY id
+        "#,
+        release_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(requirements.into_iter().map(|r| r.specifier).collect())
+}
+
+// Same resolution as PyPI: the latest final release which isn't yanked.
+pub fn latest_release(releases: &[ReleaseRow]) -> Option<&ReleaseRow> {
+    let available = releases
+        .iter()
+        .filter(|r| !r.yanked)
+        .map(|r| r.version.as_str());
+    let all = releases.iter().map(|r| r.version.as_str());
+
+    let latest = latest_version(available).or_else(|| latest_version(all))?;
+
+    releases.iter().find(|r| r.version == latest)
+}
+
+// Serial of the last change of the project.
+pub async fn get_last_serial(pool: &PgPool, project: &str) -> Result<i64, PackageError> {
+    let serial = sqlx::query!(
+        r#"
+        SELECT max(id) as serial
+        FROM journals
+        WHERE index_id = $1
+            AND normalize_pep426_name(name) = normalize_pep426_name($2)
+        "#,
+        ROOT_INDEX_ID,
+        project
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(serial.serial.unwrap_or_default() as i64)
+}
+
+pub async fn list_projects(pool: &PgPool) -> Result<Vec<String>, PackageError> {
+    let projects = sqlx::query!(
+        r#"
+        SELECT name FROM projects
+        WHERE index_id = $1
+        ORDER BY name ASC
+        "#,
+        ROOT_INDEX_ID
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(projects.into_iter().map(|p| p.name).collect())
+}
+
+pub async fn list_projects_with_serial(pool: &PgPool) -> Result<Vec<(String, i64)>, PackageError> {
+    let projects = sqlx::query!(
+        r#"
+        SELECT p.name, COALESCE(max(j.id), 0) as "serial!"
+        FROM projects p
+        LEFT JOIN journals j
+            ON j.index_id = p.index_id
+            AND normalize_pep426_name(j.name) = p.normalized_name
+        WHERE p.index_id = $1
+        GROUP BY p.name
+        ORDER BY p.name ASC
+        "#,
+        ROOT_INDEX_ID
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(projects
+        .into_iter()
+        .map(|p| (p.name, p.serial as i64))
+        .collect())
+}
+
+pub struct JournalRow {
+    pub id: i32,
+    pub name: String,
+    pub version: Option<String>,
+    pub action: String,
+    pub timestamp: i64,
+}
+
+pub async fn get_last_changelog_serial(pool: &PgPool) -> Result<i64, PackageError> {
+    let serial = sqlx::query!(
+        "SELECT max(id) as serial FROM journals WHERE index_id = $1",
+        ROOT_INDEX_ID
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(serial.serial.unwrap_or_default() as i64)
+}
+
+// Mirrors poll this, so the page size is bounded like on PyPI.
+pub async fn get_changelog_since(
+    pool: &PgPool,
+    serial: i64,
+) -> Result<Vec<JournalRow>, PackageError> {
+    let entries = sqlx::query_as!(
+        JournalRow,
+        r#"
+        SELECT
+            id,
+            name,
+            version,
+            action,
+            extract(epoch from submitted_date)::BIGINT as "timestamp!"
+        FROM journals
+        WHERE id > $1::BIGINT
+            AND index_id = $2
+        ORDER BY id ASC
+        LIMIT 50000
+        "#,
+        serial,
+        ROOT_INDEX_ID
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
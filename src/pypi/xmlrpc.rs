@@ -0,0 +1,545 @@
+use axum::{
+    extract::Extension,
+    response::{IntoResponse, Response},
+};
+use hyper::header;
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::json::project_json;
+use super::queries::{
+    get_changelog_since, get_last_changelog_serial, get_project, get_releases, latest_release,
+    list_projects, list_projects_with_serial,
+};
+use crate::simple::{simple_api::PackageError, version::Version};
+
+// XML-RPC legacy API, still used by mirroring tools.
+// https://warehouse.pypa.io/api-reference/xml-rpc.html
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    Double(f64),
+    String(String),
+    DateTime(String),
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+    Nil,
+}
+
+impl Value {
+    fn write(&self, out: &mut String) {
+        out.push_str("<value>");
+        match self {
+            Value::Int(i) => out.push_str(&format!("<int>{}</int>", i)),
+            Value::Bool(b) => out.push_str(&format!("<boolean>{}</boolean>", *b as u8)),
+            Value::Double(d) => out.push_str(&format!("<double>{}</double>", d)),
+            Value::String(s) => out.push_str(&format!("<string>{}</string>", escape(s))),
+            Value::DateTime(d) => out.push_str(&format!(
+                "<dateTime.iso8601>{}</dateTime.iso8601>",
+                escape(d)
+            )),
+            Value::Array(values) => {
+                out.push_str("<array><data>");
+                values.iter().for_each(|v| v.write(out));
+                out.push_str("</data></array>");
+            }
+            Value::Struct(members) => {
+                out.push_str("<struct>");
+                for (name, value) in members {
+                    out.push_str(&format!("<member><name>{}</name>", escape(name)));
+                    value.write(out);
+                    out.push_str("</member>");
+                }
+                out.push_str("</struct>");
+            }
+            Value::Nil => out.push_str("<nil/>"),
+        }
+        out.push_str("</value>");
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            Value::Int(i) => Some(*i != 0),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Nil,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Double(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(values) => {
+                Value::Array(values.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(members) => Value::Struct(
+                members
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value)
+        .map(Value::from)
+        .unwrap_or(Value::Nil)
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum Fault {
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+    #[error("Internal error")]
+    Internal,
+}
+
+impl Fault {
+    // Codes of the specification for fault code interoperability.
+    fn code(&self) -> i64 {
+        match self {
+            Fault::Parse(_) => -32700,
+            Fault::MethodNotFound(_) => -32601,
+            Fault::InvalidParams(_) => -32602,
+            Fault::Internal => -32500,
+        }
+    }
+}
+
+impl From<quick_xml::Error> for Fault {
+    fn from(e: quick_xml::Error) -> Self {
+        Fault::Parse(e.to_string())
+    }
+}
+
+impl From<PackageError> for Fault {
+    fn from(e: PackageError) -> Self {
+        tracing::error!("XML-RPC call failed: {}", e);
+        Fault::Internal
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MethodCall {
+    pub name: String,
+    pub params: Vec<Value>,
+}
+
+// Text content up to the end of the current element.
+fn read_text(reader: &mut Reader<&[u8]>) -> Result<String, Fault> {
+    let mut text = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Text(t) => text.push_str(&t.unescape()?),
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(_) => return Ok(text),
+            Event::Eof => return Err(Fault::Parse("Unexpected end of document".into())),
+            _ => return Err(Fault::Parse("Unexpected element in text".into())),
+        }
+    }
+}
+
+fn expect_end(reader: &mut Reader<&[u8]>) -> Result<(), Fault> {
+    match reader.read_event()? {
+        Event::End(_) => Ok(()),
+        _ => Err(Fault::Parse("Expected closing tag".into())),
+    }
+}
+
+fn scalar(tag: &[u8], text: String) -> Result<Value, Fault> {
+    let invalid = || Fault::Parse(format!("Invalid {} value", String::from_utf8_lossy(tag)));
+
+    match tag {
+        b"string" => Ok(Value::String(text)),
+        b"int" | b"i4" | b"i8" => text.trim().parse().map(Value::Int).map_err(|_| invalid()),
+        b"boolean" => match text.trim() {
+            "0" => Ok(Value::Bool(false)),
+            "1" => Ok(Value::Bool(true)),
+            _ => Err(invalid()),
+        },
+        b"double" => text
+            .trim()
+            .parse()
+            .map(Value::Double)
+            .map_err(|_| invalid()),
+        b"dateTime.iso8601" => Ok(Value::DateTime(text)),
+        b"nil" => Ok(Value::Nil),
+        _ => Err(invalid()),
+    }
+}
+
+// Parses a value once its opening tag has been read.
+fn read_value(reader: &mut Reader<&[u8]>) -> Result<Value, Fault> {
+    let value = match reader.read_event()? {
+        // Values without type are strings.
+        Event::Text(t) => {
+            let value = Value::String(t.unescape()?.into_owned());
+            expect_end(reader)?;
+            return Ok(value);
+        }
+        Event::End(_) => return Ok(Value::String(String::new())),
+        Event::Empty(e) => scalar(e.name().as_ref(), String::new())?,
+        Event::Start(e) => read_typed(reader, &e)?,
+        _ => return Err(Fault::Parse("Invalid value".into())),
+    };
+
+    expect_end(reader)?;
+    Ok(value)
+}
+
+fn read_typed(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Value, Fault> {
+    match start.name().as_ref() {
+        b"array" => {
+            let mut values = Vec::new();
+            loop {
+                match reader.read_event()? {
+                    Event::Start(e) if e.name().as_ref() == b"value" => {
+                        values.push(read_value(reader)?)
+                    }
+                    Event::Start(e) if e.name().as_ref() == b"data" => {}
+                    Event::Empty(e) if e.name().as_ref() == b"data" => {}
+                    Event::End(e) if e.name().as_ref() == b"data" => {}
+                    Event::End(e) if e.name().as_ref() == b"array" => break,
+                    _ => return Err(Fault::Parse("Invalid array".into())),
+                }
+            }
+            Ok(Value::Array(values))
+        }
+        b"struct" => {
+            let mut members = Vec::new();
+            let mut name = None;
+            loop {
+                match reader.read_event()? {
+                    Event::Start(e) if e.name().as_ref() == b"member" => {}
+                    Event::Start(e) if e.name().as_ref() == b"name" => {
+                        name = Some(read_text(reader)?)
+                    }
+                    Event::Start(e) if e.name().as_ref() == b"value" => {
+                        let name = name
+                            .take()
+                            .ok_or_else(|| Fault::Parse("Member without name".into()))?;
+                        members.push((name, read_value(reader)?));
+                    }
+                    Event::End(e) if e.name().as_ref() == b"member" => {}
+                    Event::End(e) if e.name().as_ref() == b"struct" => break,
+                    _ => return Err(Fault::Parse("Invalid struct".into())),
+                }
+            }
+            Ok(Value::Struct(members))
+        }
+        tag => scalar(tag, read_text(reader)?),
+    }
+}
+
+impl std::str::FromStr for MethodCall {
+    type Err = Fault;
+
+    fn from_str(body: &str) -> Result<Self, Self::Err> {
+        let mut reader = Reader::from_str(body);
+        reader.trim_text(true);
+
+        let mut name = None;
+        let mut params = Vec::new();
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => match e.name().as_ref() {
+                    b"methodName" => name = Some(read_text(&mut reader)?.trim().to_string()),
+                    b"value" => params.push(read_value(&mut reader)?),
+                    b"methodCall" | b"params" | b"param" => {}
+                    _ => return Err(Fault::Parse("Invalid method call".into())),
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or_else(|| Fault::Parse("Missing method name".into()))?;
+
+        Ok(MethodCall { name, params })
+    }
+}
+
+fn method_response(value: Value) -> String {
+    let mut out = String::from(r#"<?xml version="1.0"?><methodResponse><params><param>"#);
+    value.write(&mut out);
+    out.push_str("</param></params></methodResponse>");
+    out
+}
+
+fn fault_response(fault: &Fault) -> String {
+    let mut out = String::from(r#"<?xml version="1.0"?><methodResponse><fault>"#);
+    Value::Struct(vec![
+        ("faultCode".to_string(), Value::Int(fault.code())),
+        ("faultString".to_string(), Value::String(fault.to_string())),
+    ])
+    .write(&mut out);
+    out.push_str("</fault></methodResponse>");
+    out
+}
+
+fn str_param(params: &[Value], index: usize) -> Result<&str, Fault> {
+    params
+        .get(index)
+        .and_then(Value::as_str)
+        .ok_or_else(|| Fault::InvalidParams(format!("Expected a string at position {}", index)))
+}
+
+// Missing projects or releases aren't faults on PyPI, the result is empty.
+fn not_found_as(result: Result<Value, PackageError>, empty: Value) -> Result<Value, Fault> {
+    match result {
+        Err(PackageError::NotFound) => Ok(empty),
+        result => Ok(result?),
+    }
+}
+
+async fn package_releases(
+    pool: &PgPool,
+    project: &str,
+    show_hidden: bool,
+) -> Result<Value, PackageError> {
+    let project = get_project(pool, project).await?;
+    let releases = get_releases(pool, project.id).await?;
+
+    // Hidden releases are all but the latest one, the version the JSON API
+    // describes.
+    if !show_hidden {
+        return Ok(Value::Array(
+            latest_release(&releases)
+                .map(|r| Value::String(r.version.clone()))
+                .into_iter()
+                .collect(),
+        ));
+    }
+
+    let mut releases: Vec<(Version, String)> = releases
+        .into_iter()
+        .filter_map(|r| Some((r.version.parse().ok()?, r.version)))
+        .collect();
+    releases.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Ok(Value::Array(
+        releases
+            .into_iter()
+            .map(|(_, version)| Value::String(version))
+            .collect(),
+    ))
+}
+
+async fn release_urls(pool: &PgPool, project: &str, version: &str) -> Result<Value, PackageError> {
+    let release = project_json(pool, project, Some(version)).await?;
+
+    let urls = release
+        .urls
+        .iter()
+        .map(|file| {
+            let mut value = to_value(file);
+            if let Value::Struct(members) = &mut value {
+                for (name, value) in members.iter_mut() {
+                    if name == "upload_time" {
+                        *value = Value::DateTime(file.upload_time.replace('-', ""));
+                    }
+                }
+            }
+            value
+        })
+        .collect();
+
+    Ok(Value::Array(urls))
+}
+
+async fn release_data(pool: &PgPool, project: &str, version: &str) -> Result<Value, PackageError> {
+    let release = project_json(pool, project, Some(version)).await?;
+
+    Ok(to_value(&release.info))
+}
+
+async fn changelog_since_serial(pool: &PgPool, serial: i64) -> Result<Value, PackageError> {
+    let entries = get_changelog_since(pool, serial).await?;
+
+    Ok(Value::Array(
+        entries
+            .into_iter()
+            .map(|e| {
+                Value::Array(vec![
+                    Value::String(e.name),
+                    e.version.map(Value::String).unwrap_or(Value::Nil),
+                    Value::Int(e.timestamp),
+                    Value::String(e.action),
+                    Value::Int(e.id as i64),
+                ])
+            })
+            .collect(),
+    ))
+}
+
+async fn dispatch(pool: &PgPool, call: MethodCall) -> Result<Value, Fault> {
+    let params = &call.params;
+
+    match call.name.as_str() {
+        "list_packages" => {
+            let projects = list_projects(pool).await?;
+            Ok(Value::Array(
+                projects.into_iter().map(Value::String).collect(),
+            ))
+        }
+        "list_packages_with_serial" => {
+            let projects = list_projects_with_serial(pool).await?;
+            Ok(Value::Struct(
+                projects
+                    .into_iter()
+                    .map(|(name, serial)| (name, Value::Int(serial)))
+                    .collect(),
+            ))
+        }
+        "package_releases" => {
+            let project = str_param(params, 0)?;
+            let show_hidden = params.get(1).and_then(Value::as_bool).unwrap_or(false);
+            not_found_as(
+                package_releases(pool, project, show_hidden).await,
+                Value::Array(Vec::new()),
+            )
+        }
+        "release_urls" => {
+            let (project, version) = (str_param(params, 0)?, str_param(params, 1)?);
+            not_found_as(
+                release_urls(pool, project, version).await,
+                Value::Array(Vec::new()),
+            )
+        }
+        "release_data" => {
+            let (project, version) = (str_param(params, 0)?, str_param(params, 1)?);
+            not_found_as(
+                release_data(pool, project, version).await,
+                Value::Struct(Vec::new()),
+            )
+        }
+        "changelog_last_serial" => Ok(Value::Int(get_last_changelog_serial(pool).await?)),
+        "changelog_since_serial" => {
+            let serial = params
+                .first()
+                .and_then(Value::as_int)
+                .ok_or_else(|| Fault::InvalidParams("Expected an integer serial".into()))?;
+            Ok(changelog_since_serial(pool, serial).await?)
+        }
+        method => Err(Fault::MethodNotFound(method.to_string())),
+    }
+}
+
+// Faults are part of the protocol, they are returned with a 200 status.
+#[tracing::instrument(name = "Pypi::XML-RPC call", skip(pool, body))]
+pub async fn handler(Extension(pool): Extension<PgPool>, body: String) -> Response {
+    let result = match body.parse::<MethodCall>() {
+        Ok(call) => {
+            tracing::info!("XML-RPC method: {}", call.name);
+            dispatch(&pool, call).await
+        }
+        Err(e) => Err(e),
+    };
+
+    let body = match result {
+        Ok(value) => method_response(value),
+        Err(fault) => fault_response(&fault),
+    };
+
+    ([(header::CONTENT_TYPE, "text/xml")], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_method_call() {
+        let call: MethodCall = r#"<?xml version="1.0"?>
+            <methodCall>
+                <methodName>package_releases</methodName>
+                <params>
+                    <param><value><string>Demo &amp; Co</string></value></param>
+                    <param><value><boolean>1</boolean></value></param>
+                    <param><value>untyped</value></param>
+                    <param><value><array><data>
+                        <value><int>42</int></value>
+                        <value><struct><member><name>a</name><value><i4>1</i4></value></member></struct></value>
+                    </data></array></value></param>
+                </params>
+            </methodCall>"#
+            .parse()
+            .unwrap();
+
+        assert_eq!(call.name, "package_releases");
+        assert_eq!(
+            call.params,
+            vec![
+                Value::String("Demo & Co".into()),
+                Value::Bool(true),
+                Value::String("untyped".into()),
+                Value::Array(vec![
+                    Value::Int(42),
+                    Value::Struct(vec![("a".into(), Value::Int(1))])
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_method_call() {
+        assert!("<methodCall></methodCall>".parse::<MethodCall>().is_err());
+        assert!(
+            "<methodCall><methodName>x</methodName><params><param><value><int>a</int></value></param></params></methodCall>"
+                .parse::<MethodCall>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn serialize_response() {
+        let value = Value::Array(vec![
+            Value::String("a<b".into()),
+            Value::Nil,
+            Value::Struct(vec![("ok".into(), Value::Bool(true))]),
+        ]);
+
+        assert_eq!(
+            method_response(value),
+            r#"<?xml version="1.0"?><methodResponse><params><param><value><array><data><value><string>a&lt;b</string></value><value><nil/></value><value><struct><member><name>ok</name><value><boolean>1</boolean></value></member></struct></value></data></array></value></param></params></methodResponse>"#
+        );
+        assert!(
+            fault_response(&Fault::MethodNotFound("search".into())).contains("<int>-32601</int>")
+        );
+    }
+}
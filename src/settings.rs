@@ -1,3 +1,10 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use object_store::{
+    aws::AmazonS3Builder, azure::MicrosoftAzureBuilder, gcp::GoogleCloudStorageBuilder,
+    local::LocalFileSystem, memory::InMemory, prefix::PrefixStore, ObjectStore,
+};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::{
@@ -6,16 +13,58 @@ use sqlx::{
 };
 use toml::de::Error;
 
+use crate::simple::store::Presigner;
+
 #[derive(Deserialize)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub persistence: PersistenceSettings,
+    pub upstream: Option<UpstreamSettings>,
+    pub mirror: Option<MirrorSettings>,
+    pub admin: Option<AdminSettings>,
 }
 
 #[derive(Deserialize)]
 pub struct ApplicationSettings {
     pub host: String,
     pub port: usize,
+    // Signs the session cookies, at least 64 bytes. Replicas share it, and
+    // sessions survive restarts.
+    pub session_key: String,
+}
+
+// Index serving the projects which aren't hosted by Nest, it must implement
+// the JSON simple API (PEP 691), e.g. `https://pypi.org/simple/`.
+#[derive(Deserialize)]
+pub struct UpstreamSettings {
+    pub url: String,
+}
+
+// Projects copied ahead of time from the upstream index, for installs which
+// can't reach it.
+#[derive(Deserialize)]
+pub struct MirrorSettings {
+    // The whole upstream index is mirrored when no project is listed.
+    #[serde(default)]
+    pub projects: Vec<String>,
+    // Regular expressions of the project names which are never mirrored.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    // Seconds between the end of a synchronisation and the start of the next one.
+    #[serde(default = "default_mirror_interval")]
+    pub interval: u64,
+}
+
+fn default_mirror_interval() -> u64 {
+    3600
+}
+
+// Account given the admin role on startup, to bootstrap the first admin. It is
+// created with the password when it doesn't exist yet.
+#[derive(Deserialize)]
+pub struct AdminSettings {
+    pub username: String,
+    pub password: String,
 }
 
 #[derive(Deserialize)]
@@ -24,9 +73,47 @@ pub struct PersistenceSettings {
     pub database: DatabaseSettings,
 }
 
+// Backend of the distribution files, selected with the `type` key.
 #[derive(Deserialize)]
-pub struct ObjectStorageSettings {
-    pub path: String,
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ObjectStorageSettings {
+    Local {
+        path: String,
+    },
+    // Credentials which are not set are read from the `AWS_*` environment
+    // variables. Set `endpoint` and `allow_http` for MinIO.
+    S3 {
+        bucket: String,
+        region: Option<String>,
+        endpoint: Option<String>,
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+        #[serde(default)]
+        allow_http: bool,
+        prefix: Option<String>,
+        // Lifetime in seconds of the presigned URLs downloads are redirected
+        // to, files are proxied by the index when not set.
+        presigned_url_ttl: Option<u64>,
+    },
+    // Credentials which are not set are read from the `AZURE_*` environment
+    // variables. `use_emulator` targets a local Azurite.
+    Azure {
+        account: String,
+        container: String,
+        access_key: Option<String>,
+        #[serde(default)]
+        use_emulator: bool,
+        prefix: Option<String>,
+        presigned_url_ttl: Option<u64>,
+    },
+    // Credentials which are not set are read from `GOOGLE_SERVICE_ACCOUNT`.
+    Gcs {
+        bucket: String,
+        service_account_path: Option<String>,
+        prefix: Option<String>,
+    },
+    // Files are lost on restart, only meant for tests.
+    Memory,
 }
 
 #[derive(Deserialize)]
@@ -64,6 +151,108 @@ impl DatabaseSettings {
     }
 }
 
+// Object store of the distribution files, and the signer of the URLs clients
+// are redirected to when downloads are not proxied.
+pub struct ObjectStorage {
+    pub store: Arc<dyn ObjectStore>,
+    pub presigner: Option<Presigner>,
+}
+
+impl ObjectStorageSettings {
+    pub fn build(&self) -> Result<ObjectStorage, object_store::Error> {
+        let (store, presigner, prefix): (Arc<dyn ObjectStore>, Option<Presigner>, _) = match self {
+            ObjectStorageSettings::Local { path } => {
+                std::fs::create_dir_all(path).map_err(|e| object_store::Error::Generic {
+                    store: "LocalFileSystem",
+                    source: Box::new(e),
+                })?;
+
+                (
+                    Arc::new(LocalFileSystem::new_with_prefix(path)?),
+                    None,
+                    &None,
+                )
+            }
+            ObjectStorageSettings::S3 {
+                bucket,
+                region,
+                endpoint,
+                access_key_id,
+                secret_access_key,
+                allow_http,
+                prefix,
+                presigned_url_ttl,
+            } => {
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(bucket)
+                    .with_allow_http(*allow_http);
+                if let Some(region) = region {
+                    builder = builder.with_region(region);
+                }
+                if let Some(endpoint) = endpoint {
+                    builder = builder.with_endpoint(endpoint);
+                }
+                if let Some(access_key_id) = access_key_id {
+                    builder = builder.with_access_key_id(access_key_id);
+                }
+                if let Some(secret_access_key) = secret_access_key {
+                    builder = builder.with_secret_access_key(secret_access_key);
+                }
+
+                let store = Arc::new(builder.build()?);
+                let presigner = presigned_url_ttl.map(|ttl| {
+                    Presigner::new(store.clone(), prefix.as_deref(), Duration::from_secs(ttl))
+                });
+                (store, presigner, prefix)
+            }
+            ObjectStorageSettings::Azure {
+                account,
+                container,
+                access_key,
+                use_emulator,
+                prefix,
+                presigned_url_ttl,
+            } => {
+                let mut builder = MicrosoftAzureBuilder::from_env()
+                    .with_account(account)
+                    .with_container_name(container)
+                    .with_use_emulator(*use_emulator);
+                if let Some(access_key) = access_key {
+                    builder = builder.with_access_key(access_key);
+                }
+
+                let store = Arc::new(builder.build()?);
+                let presigner = presigned_url_ttl.map(|ttl| {
+                    Presigner::new(store.clone(), prefix.as_deref(), Duration::from_secs(ttl))
+                });
+                (store, presigner, prefix)
+            }
+            ObjectStorageSettings::Gcs {
+                bucket,
+                service_account_path,
+                prefix,
+            } => {
+                let mut builder = GoogleCloudStorageBuilder::from_env().with_bucket_name(bucket);
+                if let Some(service_account_path) = service_account_path {
+                    builder = builder.with_service_account_path(service_account_path);
+                }
+
+                (Arc::new(builder.build()?), None, prefix)
+            }
+            ObjectStorageSettings::Memory => (Arc::new(InMemory::new()), None, &None),
+        };
+
+        // Several Nest deployments can share a bucket, each one under its own
+        // prefix. The indexes of a deployment share its prefix.
+        let store = match prefix {
+            Some(prefix) => Arc::new(PrefixStore::new(store, prefix.as_str())),
+            None => store,
+        };
+
+        Ok(ObjectStorage { store, presigner })
+    }
+}
+
 pub fn get_settings(path: Option<String>) -> Result<Settings, Error> {
     let path = path.unwrap_or(String::from("config.default.toml"));
 
@@ -0,0 +1,129 @@
+use std::io::{Read, Seek};
+
+use flate2::read::GzDecoder;
+
+use super::package::PackageType;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Unsupported distribution format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("No metadata file found in {0}")]
+    MissingMetadata(String),
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+// Extract the core metadata file of a distribution.
+//
+// - Wheels hold it in `{name}-{version}.dist-info/METADATA` (PEP 427).
+// - Source distributions hold it in `{name}-{version}/PKG-INFO` (PEP 643).
+pub fn extract_metadata_file(
+    package_type: PackageType,
+    filename: &str,
+    content: impl Read + Seek,
+) -> Result<Vec<u8>, ArchiveError> {
+    match package_type {
+        PackageType::BdistWheel => read_zip_entry(filename, content, is_wheel_metadata),
+        PackageType::Sdist => read_tar_gz_entry(filename, content, is_sdist_metadata),
+        _ => Err(ArchiveError::UnsupportedFormat(filename.to_string())),
+    }
+}
+
+// PEP 427 - The `WHEEL` file holds the wheel version and the tags of the build.
+pub fn extract_wheel_file(
+    filename: &str,
+    content: impl Read + Seek,
+) -> Result<Vec<u8>, ArchiveError> {
+    read_zip_entry(filename, content, is_wheel_file)
+}
+
+fn is_wheel_file(path: &str) -> bool {
+    match path.split_once('/') {
+        Some((dir, file)) => dir.ends_with(".dist-info") && file == "WHEEL",
+        None => false,
+    }
+}
+
+fn is_wheel_metadata(path: &str) -> bool {
+    match path.split_once('/') {
+        Some((dir, file)) => dir.ends_with(".dist-info") && file == "METADATA",
+        None => false,
+    }
+}
+
+fn is_sdist_metadata(path: &str) -> bool {
+    let path = path.trim_start_matches("./");
+
+    match path.split_once('/') {
+        Some((dir, file)) => !dir.is_empty() && file == "PKG-INFO",
+        None => false,
+    }
+}
+
+fn read_zip_entry(
+    filename: &str,
+    content: impl Read + Seek,
+    is_metadata: fn(&str) -> bool,
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(content)?;
+
+    let entry_name = archive
+        .file_names()
+        .find(|name| is_metadata(name))
+        .map(str::to_string)
+        .ok_or_else(|| ArchiveError::MissingMetadata(filename.to_string()))?;
+
+    let mut entry = archive.by_name(&entry_name)?;
+    let mut metadata = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut metadata)?;
+
+    Ok(metadata)
+}
+
+fn read_tar_gz_entry(
+    filename: &str,
+    content: impl Read,
+    is_metadata: fn(&str) -> bool,
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(content));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+
+        if is_metadata(&path) {
+            let mut metadata = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut metadata)?;
+
+            return Ok(metadata);
+        }
+    }
+
+    Err(ArchiveError::MissingMetadata(filename.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_sdist_metadata, is_wheel_metadata};
+
+    #[test]
+    fn wheel_metadata_lives_in_top_level_dist_info() {
+        assert!(is_wheel_metadata("nest-0.1.0.dist-info/METADATA"));
+        assert!(!is_wheel_metadata("nest/METADATA"));
+        assert!(!is_wheel_metadata("nest/vendor/foo-1.0.dist-info/METADATA"));
+    }
+
+    #[test]
+    fn sdist_metadata_lives_in_top_level_directory() {
+        assert!(is_sdist_metadata("nest-0.1.0/PKG-INFO"));
+        assert!(is_sdist_metadata("./nest-0.1.0/PKG-INFO"));
+        assert!(!is_sdist_metadata("PKG-INFO"));
+        assert!(!is_sdist_metadata("nest-0.1.0/nest.egg-info/PKG-INFO"));
+    }
+}
@@ -0,0 +1,184 @@
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::{header, HeaderMap};
+
+use super::package::DistFile;
+
+// A byte range which doesn't overlap the file, answered with a 416.
+#[derive(Debug, PartialEq)]
+pub struct RangeNotSatisfiable;
+
+// Parse a `Range` header, only single ranges are served. Other requests,
+// like multiple ranges, fall back to the whole file as allowed by RFC 9110.
+pub fn parse_range(
+    value: &str,
+    size: usize,
+) -> Result<Option<Range<usize>>, RangeNotSatisfiable> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        // Suffix range, the last bytes of the file.
+        ("", suffix) => match suffix.parse::<usize>() {
+            Ok(0) => return Err(RangeNotSatisfiable),
+            Ok(suffix) => size.saturating_sub(suffix)..size,
+            Err(_) => return Ok(None),
+        },
+        (start, "") => match start.parse::<usize>() {
+            Ok(start) => start..size,
+            Err(_) => return Ok(None),
+        },
+        (start, end) => match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => start..size.min(end + 1),
+            _ => return Ok(None),
+        },
+    };
+
+    if range.start >= size {
+        return Err(RangeNotSatisfiable);
+    }
+
+    Ok(Some(range))
+}
+
+// Strong validator of the file, its content never changes once uploaded.
+pub fn etag(file: &DistFile) -> String {
+    format!("\"{}\"", file.sha256_digest)
+}
+
+// HTTP dates have a one second resolution.
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+pub fn last_modified(file: &DistFile) -> String {
+    httpdate::fmt_http_date(file.last_modified)
+}
+
+fn etag_matches(value: &str, etag: &str) -> bool {
+    value
+        .split(',')
+        .map(|v| v.trim())
+        .any(|v| v == "*" || v.trim_start_matches("W/") == etag)
+}
+
+// Conditional GET, `If-None-Match` takes precedence over `If-Modified-Since`.
+pub fn is_not_modified(headers: &HeaderMap, file: &DistFile) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        return value
+            .to_str()
+            .map(|v| etag_matches(v, &etag(file)))
+            .unwrap_or(false);
+    }
+
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+        .map(|since| truncate_to_seconds(file.last_modified) <= since)
+        .unwrap_or(false)
+}
+
+// The range is ignored when `If-Range` doesn't match the current file.
+pub fn requested_range(
+    headers: &HeaderMap,
+    file: &DistFile,
+) -> Result<Option<Range<usize>>, RangeNotSatisfiable> {
+    let Some(range) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return Ok(None);
+    };
+
+    if let Some(if_range) = headers.get(header::IF_RANGE) {
+        let if_range = if_range.to_str().unwrap_or_default().trim();
+        let matches = match httpdate::parse_http_date(if_range) {
+            Ok(date) => truncate_to_seconds(file.last_modified) == date,
+            Err(_) => if_range == etag(file),
+        };
+        if !matches {
+            return Ok(None);
+        }
+    }
+
+    parse_range(range, file.size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist_file() -> DistFile {
+        DistFile {
+            path: "simple-index/demo/demo-0.1.0.tar.gz".into(),
+            filename: "demo-0.1.0.tar.gz".into(),
+            size: 100,
+            sha256_digest: "abc".into(),
+            last_modified: UNIX_EPOCH + Duration::from_millis(1_700_000_000_500),
+        }
+    }
+
+    #[test]
+    fn parse_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Ok(Some(0..10)));
+        assert_eq!(parse_range("bytes=90-", 100), Ok(Some(90..100)));
+        assert_eq!(parse_range("bytes=-10", 100), Ok(Some(90..100)));
+        assert_eq!(parse_range("bytes=-500", 100), Ok(Some(0..100)));
+        assert_eq!(parse_range("bytes=50-500", 100), Ok(Some(50..100)));
+        assert_eq!(parse_range("bytes=100-", 100), Err(RangeNotSatisfiable));
+        assert_eq!(parse_range("bytes=-0", 100), Err(RangeNotSatisfiable));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), Ok(None));
+        assert_eq!(parse_range("bytes=9-0", 100), Ok(None));
+        assert_eq!(parse_range("items=0-9", 100), Ok(None));
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let file = dist_file();
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_not_modified(&headers, &file));
+
+        headers.insert(header::IF_NONE_MATCH, "\"other\", W/\"abc\"".parse().unwrap());
+        assert!(is_not_modified(&headers, &file));
+
+        // If-None-Match wins over a matching If-Modified-Since.
+        headers.insert(header::IF_NONE_MATCH, "\"other\"".parse().unwrap());
+        headers.insert(
+            header::IF_MODIFIED_SINCE,
+            last_modified(&file).parse().unwrap(),
+        );
+        assert!(!is_not_modified(&headers, &file));
+
+        headers.remove(header::IF_NONE_MATCH);
+        assert!(is_not_modified(&headers, &file));
+    }
+
+    #[test]
+    fn range_depends_on_if_range() {
+        let file = dist_file();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=10-".parse().unwrap());
+        assert_eq!(requested_range(&headers, &file), Ok(Some(10..100)));
+
+        headers.insert(header::IF_RANGE, "\"abc\"".parse().unwrap());
+        assert_eq!(requested_range(&headers, &file), Ok(Some(10..100)));
+
+        headers.insert(header::IF_RANGE, "\"changed\"".parse().unwrap());
+        assert_eq!(requested_range(&headers, &file), Ok(None));
+
+        headers.insert(header::IF_RANGE, last_modified(&file).parse().unwrap());
+        assert_eq!(requested_range(&headers, &file), Ok(Some(10..100)));
+    }
+}
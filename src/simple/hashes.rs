@@ -0,0 +1,111 @@
+use blake2::{digest::consts::U32, Blake2b};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+type Blake2b256 = Blake2b<U32>;
+
+pub fn md5_digest(content: &[u8]) -> String {
+    format!("{:x}", Md5::digest(content))
+}
+
+pub fn sha256_digest(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+pub fn blake2_256_digest(content: &[u8]) -> String {
+    format!("{:x}", Blake2b256::digest(content))
+}
+
+// Computes the digests of a file chunk by chunk, while it is received.
+#[derive(Default)]
+pub struct Hasher {
+    md5: Md5,
+    sha256: Sha256,
+    blake2_256: Blake2b256,
+}
+
+impl Hasher {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.md5.update(chunk);
+        self.sha256.update(chunk);
+        self.blake2_256.update(chunk);
+    }
+
+    // Hex encoded md5, sha256 and blake2_256 digests.
+    pub fn finalize(self) -> (String, String, String) {
+        (
+            format!("{:x}", self.md5.finalize()),
+            format!("{:x}", self.sha256.finalize()),
+            format!("{:x}", self.blake2_256.finalize()),
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("The {algorithm} digest provided ({provided}) does not match the digest of the uploaded file ({computed}).")]
+pub struct DigestError {
+    pub algorithm: &'static str,
+    pub provided: String,
+    pub computed: String,
+}
+
+// Compare a digest sent by the client with the one computed by the server.
+// Missing or empty digests are accepted, the computed digest is used instead.
+pub fn verify_digest(
+    algorithm: &'static str,
+    provided: Option<&str>,
+    computed: String,
+) -> Result<String, DigestError> {
+    match provided.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(provided) if !provided.eq_ignore_ascii_case(&computed) => Err(DigestError {
+            algorithm,
+            provided: provided.to_string(),
+            computed,
+        }),
+        _ => Ok(computed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sha256_digest, verify_digest, Hasher};
+
+    #[test]
+    fn hasher_matches_digests_of_the_whole_content() {
+        let mut hasher = Hasher::default();
+        hasher.update(b"ne");
+        hasher.update(b"st");
+
+        let (md5, sha256, blake2_256) = hasher.finalize();
+        assert_eq!(md5, super::md5_digest(b"nest"));
+        assert_eq!(sha256, sha256_digest(b"nest"));
+        assert_eq!(blake2_256, super::blake2_256_digest(b"nest"));
+    }
+
+    #[test]
+    fn missing_digest_is_filled_in() {
+        let computed = sha256_digest(b"nest");
+        assert_eq!(
+            verify_digest("sha256", None, computed.clone()).unwrap(),
+            computed
+        );
+        assert_eq!(
+            verify_digest("sha256", Some(""), computed.clone()).unwrap(),
+            computed
+        );
+    }
+
+    #[test]
+    fn digest_comparison_ignores_case() {
+        let computed = sha256_digest(b"nest");
+        let provided = computed.to_uppercase();
+        assert!(verify_digest("sha256", Some(&provided), computed).is_ok());
+    }
+
+    #[test]
+    fn mismatching_digest_is_rejected() {
+        let computed = sha256_digest(b"nest");
+        let provided = sha256_digest(b"tampered");
+        assert!(verify_digest("sha256", Some(&provided), computed).is_err());
+    }
+}
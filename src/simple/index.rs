@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Path},
+    http::request::Parts,
+};
+use serde::{Deserialize, Serialize};
+
+use super::package::normalize_project_name;
+use super::simple_api::{PackageError, SimpleState};
+
+// Index of the projects created before named indexes, served under `/simple`.
+pub const ROOT_INDEX: &str = "root";
+pub const ROOT_INDEX_ID: i32 = 1;
+
+// How an index serves a project, instead of the first of the index and its
+// parents which has it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "lowercase")]
+pub enum IndexRule {
+    // Only the files of the given index, the index itself or one of its parents.
+    Pin { index: String },
+    // The files of the index and of all its parents.
+    Merge,
+    // None, the project is hidden.
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectOverride {
+    pub project: String,
+    #[serde(flatten)]
+    pub rule: IndexRule,
+}
+
+// Parents are listed by priority, the first one is looked at first.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    #[serde(default)]
+    pub parents: Vec<String>,
+    #[serde(default)]
+    pub overrides: Vec<ProjectOverride>,
+}
+
+fn invalid(message: String) -> PackageError {
+    PackageError::InvalidIndex(message)
+}
+
+// Names are part of the URLs of the index.
+pub fn validate_index_name(name: &str) -> Result<(), PackageError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+
+    if !valid {
+        return Err(invalid(format!(
+            "{} must be made of lowercase letters, digits and dashes",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+impl IndexDefinition {
+    // Checks what can be checked without the other indexes, cycles are
+    // checked when the definition is saved.
+    pub fn validate(&self, name: &str) -> Result<(), PackageError> {
+        validate_index_name(name)?;
+
+        if name == ROOT_INDEX {
+            return Err(invalid(String::from("the root index can't be redefined")));
+        }
+
+        for (i, parent) in self.parents.iter().enumerate() {
+            if parent == name {
+                return Err(invalid(format!("{} can't be its own parent", name)));
+            }
+            if self.parents[..i].contains(parent) {
+                return Err(invalid(format!("{} is listed twice", parent)));
+            }
+        }
+
+        let mut projects = Vec::with_capacity(self.overrides.len());
+        for project_override in &self.overrides {
+            let project = normalize_project_name(&project_override.project);
+            if projects.contains(&project) {
+                return Err(invalid(format!("{} has two rules", project)));
+            }
+
+            if let IndexRule::Pin { index } = &project_override.rule {
+                if index != name && !self.parents.contains(index) {
+                    return Err(invalid(format!(
+                        "{} is pinned to {} which isn't a parent",
+                        project, index
+                    )));
+                }
+            }
+
+            projects.push(project);
+        }
+
+        Ok(())
+    }
+}
+
+// Store of the index the request targets, with the path its pages link to.
+// Routes without an `index` parameter target the root index.
+pub struct Index {
+    pub name: String,
+    pub store: SimpleState,
+    pub base: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Index
+where
+    SimpleState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = PackageError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let store = SimpleState::from_ref(state);
+        let params = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map(|Path(params)| params)
+            .unwrap_or_default();
+
+        match params.get("index") {
+            Some(name) => Ok(Index {
+                name: name.to_owned(),
+                store: store.get_index(name).await?,
+                base: format!("/indexes/{}/simple", name),
+            }),
+            None => Ok(Index {
+                name: String::from(ROOT_INDEX),
+                store,
+                base: String::from("/simple"),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definitions_are_read_from_json() {
+        let definition: IndexDefinition = serde_json::from_str(
+            r#"{
+                "parents": ["shared", "root"],
+                "overrides": [
+                    {"project": "numpy", "rule": "pin", "index": "root"},
+                    {"project": "internal-tool", "rule": "block"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(definition.parents, ["shared", "root"]);
+        assert_eq!(
+            definition.overrides[0].rule,
+            IndexRule::Pin {
+                index: String::from("root")
+            }
+        );
+        assert_eq!(definition.overrides[1].rule, IndexRule::Block);
+        assert!(definition.validate("team-ml").is_ok());
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        let definition = |parents: &[&str], overrides: Vec<ProjectOverride>| IndexDefinition {
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            overrides,
+        };
+        let pin = |project: &str, index: &str| ProjectOverride {
+            project: project.to_owned(),
+            rule: IndexRule::Pin {
+                index: index.to_owned(),
+            },
+        };
+
+        assert!(definition(&["root"], vec![]).validate("Team_ML").is_err());
+        assert!(definition(&["root"], vec![]).validate("root").is_err());
+        assert!(definition(&["team"], vec![]).validate("team").is_err());
+        assert!(definition(&["root", "root"], vec![])
+            .validate("team")
+            .is_err());
+        assert!(definition(&["root"], vec![pin("numpy", "other")])
+            .validate("team")
+            .is_err());
+        assert!(
+            definition(&["root"], vec![pin("numpy", "team"), pin("NumPy", "root")])
+                .validate("team")
+                .is_err()
+        );
+        assert!(definition(&["root"], vec![pin("numpy", "team")])
+            .validate("team")
+            .is_ok());
+    }
+}
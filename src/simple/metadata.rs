@@ -0,0 +1,265 @@
+use super::archive::ArchiveError;
+use super::package::{normalize_project_name, CoreMetadata};
+use super::version::same_version;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    #[error("Missing required metadata field {0}.")]
+    MissingField(&'static str),
+
+    #[error("The {field} sent with the upload ({form}) does not match the {field} of the distribution metadata ({file}).")]
+    Mismatch {
+        field: &'static str,
+        form: String,
+        file: String,
+    },
+
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+}
+
+// Core metadata specifications
+// https://packaging.python.org/en/latest/specifications/core-metadata/
+//
+// `METADATA` and `PKG-INFO` files use the RFC 822 email header format.
+// Some fields may be used several times and the description may be provided
+// in the message body instead of the `Description` header.
+struct RawMetadata {
+    fields: Vec<(String, Vec<String>)>,
+    body: Option<String>,
+}
+
+impl RawMetadata {
+    fn parse(raw: &str) -> Self {
+        let raw = raw.replace("\r\n", "\n");
+
+        let (headers, body) = match raw.split_once("\n\n") {
+            Some((headers, body)) => (headers, Some(body)),
+            None => (raw.as_str(), None),
+        };
+
+        let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+
+        for line in headers.lines() {
+            if line.starts_with([' ', '\t']) {
+                // Continuation line of a folded field.
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push(line.to_string());
+                }
+            } else if let Some((key, value)) = line.split_once(':') {
+                fields.push((key.trim().to_lowercase(), vec![value.trim().to_string()]));
+            }
+        }
+
+        let body = body
+            .map(|b| b.trim_end().to_string())
+            .filter(|b| !b.trim().is_empty());
+
+        RawMetadata { fields, body }
+    }
+
+    fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Vec<String>> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, lines)| lines)
+    }
+
+    // Folded lines are joined back into a single line.
+    fn get(&self, key: &str) -> Option<String> {
+        self.values(key)
+            .next()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.trim())
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .filter(|v| !v.is_empty())
+    }
+
+    // Folded lines keep their line breaks, the indentation (and the optional
+    // `|` marker used by setuptools) is removed.
+    fn get_multiline(&self, key: &str) -> Option<String> {
+        self.values(key)
+            .next()
+            .map(|lines| {
+                let mut value = lines[0].clone();
+                for line in &lines[1..] {
+                    let line = line.strip_prefix("        ").unwrap_or(line.trim_start());
+                    let line = line
+                        .strip_prefix("       |")
+                        .or_else(|| line.strip_prefix('|'))
+                        .unwrap_or(line);
+                    value.push('\n');
+                    value.push_str(line);
+                }
+                value.trim_end().to_string()
+            })
+            .filter(|v| !v.is_empty())
+    }
+
+    fn get_all(&self, key: &str) -> Vec<String> {
+        self.values(key)
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.trim())
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    fn require(&self, key: &str, field: &'static str) -> Result<String, MetadataError> {
+        self.get(key).ok_or(MetadataError::MissingField(field))
+    }
+}
+
+pub fn parse_metadata(raw: &str) -> Result<CoreMetadata, MetadataError> {
+    let raw = RawMetadata::parse(raw);
+
+    let keywords = raw
+        .get("keywords")
+        .map(|kw| {
+            let separator = if kw.contains(',') { ',' } else { ' ' };
+            kw.split(separator)
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let description = raw.body.clone().or_else(|| raw.get_multiline("description"));
+
+    Ok(CoreMetadata {
+        metadata_version: raw.require("metadata-version", "Metadata-Version")?,
+        name: raw.require("name", "Name")?,
+        version: raw.require("version", "Version")?,
+        platforms: raw.get_all("platform"),
+        supported_platforms: raw.get_all("supported-platform"),
+        summary: raw.get("summary"),
+        description,
+        description_content_type: raw.get("description-content-type"),
+        keywords,
+        home_page: raw.get("home-page"),
+        download_url: raw.get("download-url"),
+        author: raw.get("author"),
+        author_email: raw.get("author-email"),
+        maintainer: raw.get("maintainer"),
+        maintainer_email: raw.get("maintainer-email"),
+        license: raw.get_multiline("license"),
+        classifiers: raw.get_all("classifier"),
+        requires_dists: raw.get_all("requires-dist"),
+        requires_python: raw.get("requires-python"),
+        requires_externals: raw.get_all("requires-external"),
+        project_urls: raw.get_all("project-url"),
+        provides_extras: raw.get_all("provides-extra"),
+        provides_dists: raw.get_all("provides-dist"),
+        obsoletes_dists: raw.get_all("obsoletes-dist"),
+    })
+}
+
+// The name and version sent by the upload client must describe the uploaded
+// distribution.
+pub fn check_form_metadata(
+    metadata: &CoreMetadata,
+    name: &str,
+    version: &str,
+) -> Result<(), MetadataError> {
+    if normalize_project_name(name) != normalize_project_name(&metadata.name) {
+        return Err(MetadataError::Mismatch {
+            field: "name",
+            form: name.to_string(),
+            file: metadata.name.to_owned(),
+        });
+    }
+
+    if !same_version(version, &metadata.version) {
+        return Err(MetadataError::Mismatch {
+            field: "version",
+            form: version.to_string(),
+            file: metadata.version.to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_form_metadata, parse_metadata};
+
+    const METADATA: &str = "Metadata-Version: 2.1
+Name: nest-example
+Version: 1.0.0
+Summary: An example package
+Author: C. Schultz, Universal Features Syndicate,
+        Los Angeles, CA
+Keywords: dog,puppy,voting
+Classifier: Programming Language :: Python :: 3
+Classifier: License :: OSI Approved :: MIT License
+Requires-Dist: requests (>=2.0)
+Requires-Dist: pytest ; extra == 'test'
+Provides-Extra: test
+Description-Content-Type: text/markdown
+
+# Nest example
+
+A description in the message body.
+";
+
+    #[test]
+    fn parse_headers_and_multiple_use_fields() {
+        let metadata = parse_metadata(METADATA).unwrap();
+
+        assert_eq!(metadata.name, "nest-example");
+        assert_eq!(metadata.version, "1.0.0");
+        assert_eq!(metadata.classifiers.len(), 2);
+        assert_eq!(metadata.requires_dists.len(), 2);
+        assert_eq!(metadata.keywords, vec!["dog", "puppy", "voting"]);
+        assert_eq!(
+            metadata.author.as_deref(),
+            Some("C. Schultz, Universal Features Syndicate, Los Angeles, CA")
+        );
+    }
+
+    #[test]
+    fn parse_description_from_message_body() {
+        let metadata = parse_metadata(METADATA).unwrap();
+
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("# Nest example\n\nA description in the message body.")
+        );
+    }
+
+    #[test]
+    fn parse_folded_description_header() {
+        let raw = "Metadata-Version: 1.1\nName: legacy\nVersion: 0.1\nDescription: First line\n        |\n        |Second paragraph\n";
+        let metadata = parse_metadata(raw).unwrap();
+
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("First line\n\nSecond paragraph")
+        );
+    }
+
+    #[test]
+    fn missing_version_is_rejected() {
+        assert!(parse_metadata("Metadata-Version: 2.1\nName: nest\n").is_err());
+    }
+
+    #[test]
+    fn form_must_match_distribution_metadata() {
+        let metadata = parse_metadata(METADATA).unwrap();
+
+        assert!(check_form_metadata(&metadata, "Nest_Example", "1.0.0").is_ok());
+        assert!(check_form_metadata(&metadata, "nest-example", "1.0").is_ok());
+        assert!(check_form_metadata(&metadata, "other", "1.0.0").is_err());
+        assert!(check_form_metadata(&metadata, "nest-example", "2.0.0").is_err());
+    }
+}
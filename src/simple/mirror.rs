@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+
+use super::simple_api::PackageError;
+use super::store::Store;
+use super::upstream::{Upstream, UpstreamProject};
+use crate::settings::MirrorSettings;
+
+// Copies projects of the upstream index ahead of time, in the spirit of
+// bandersnatch. Each project is checkpointed with the serial it was copied
+// at, so unchanged projects are skipped and an interrupted synchronisation
+// resumes where it stopped.
+pub struct Mirror {
+    store: Arc<Store>,
+    upstream: Upstream,
+    projects: Vec<String>,
+    exclude: Vec<Regex>,
+    interval: Duration,
+}
+
+impl Mirror {
+    pub fn new(
+        store: Arc<Store>,
+        upstream: Upstream,
+        settings: &MirrorSettings,
+    ) -> Result<Mirror, regex::Error> {
+        let exclude = settings
+            .exclude
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<Regex>, regex::Error>>()?;
+
+        Ok(Mirror {
+            store,
+            upstream,
+            projects: settings.projects.to_owned(),
+            exclude,
+            interval: Duration::from_secs(settings.interval),
+        })
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.sync().await {
+                tracing::error!("Unable to synchronise the mirror: {}", e);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    // A failing project doesn't stop the synchronisation, it is retried on
+    // the next one.
+    pub async fn sync(&self) -> Result<(), PackageError> {
+        let projects = self.selected_projects().await?;
+        tracing::info!("Synchronise {} projects of the mirror", projects.len());
+
+        for project in projects {
+            if let Err(e) = self.sync_project(&project).await {
+                tracing::warn!("Unable to mirror {}: {}", project.name, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn selected_projects(&self) -> Result<Vec<UpstreamProject>, PackageError> {
+        let projects = match self.projects.is_empty() {
+            true => self.upstream.get_projects().await?,
+            false => self
+                .projects
+                .iter()
+                .map(|name| UpstreamProject {
+                    name: name.to_owned(),
+                    last_serial: None,
+                })
+                .collect(),
+        };
+
+        Ok(projects
+            .into_iter()
+            .filter(|p| !self.exclude.iter().any(|e| e.is_match(&p.name)))
+            .collect())
+    }
+
+    async fn sync_project(&self, project: &UpstreamProject) -> Result<(), PackageError> {
+        let checkpoint = self.store.get_mirror_serial(&project.name).await?;
+
+        // The project list of the upstream index spares a request per
+        // unchanged project.
+        if project.last_serial.is_some() && project.last_serial == checkpoint {
+            return Ok(());
+        }
+
+        let files = self.upstream.get_project_files(&project.name).await?;
+        let serial = files.last_serial.or(project.last_serial);
+
+        if serial.is_some() && serial == checkpoint {
+            return Ok(());
+        }
+
+        let copied = self
+            .store
+            .mirror_project(&self.upstream, &project.name, &files.files)
+            .await?;
+        self.store.set_mirror_serial(&project.name, serial).await?;
+
+        if copied > 0 {
+            tracing::info!("Mirror {} new files of {}", copied, project.name);
+        }
+
+        Ok(())
+    }
+}
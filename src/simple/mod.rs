@@ -1,26 +1,47 @@
 use axum_template::RenderHtml;
 
 use axum::{
-    extract::{DefaultBodyLimit, Path, State},
-    response::IntoResponse,
+    body::Body,
+    extract::{DefaultBodyLimit, Extension, Path},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router,
+    Json, Router,
 };
 use axum_typed_multipart::TypedMultipart;
-use hyper::{header, StatusCode};
-use serde::Serialize;
+use hyper::{header, HeaderMap, Method, StatusCode};
+use serde::{Deserialize, Serialize};
 
+pub mod archive;
+pub mod download;
+pub mod hashes;
+pub mod index;
+pub mod metadata;
+pub mod mirror;
 pub mod models;
+pub mod negotiation;
 pub mod package;
+pub mod requirement;
 pub mod simple_api;
 pub mod store;
+pub mod upstream;
+pub mod version;
+pub mod wheel;
 
-use crate::{authentication::auth, engine::AppEngine, state::AppState};
-use models::RequestData;
+use crate::{
+    authentication::{auth, User},
+    engine::AppEngine,
+    state::AppState,
+    telemetry::spawn_blocking_with_tracing,
+};
+use index::Index;
+use models::{ProjectDetailJson, ProjectListJson, RequestData};
+use negotiation::SimpleFormat;
 use package::Distribution;
 
-use self::simple_api::SimpleState;
+use self::simple_api::{PackageError, PkgDist};
 
+// Served under `/simple` for the root index, and under
+// `/indexes/:index/simple` for the named indexes.
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(upload))
@@ -33,93 +54,259 @@ pub fn router() -> Router<AppState> {
 
 #[tracing::instrument(
         name = "Simple::Upload a package",
-        skip(store, data),
+        skip(index, user, data),
         fields(
             project = %data.name,
-            project_version = %data.version
+            project_version = %data.version,
+            username = %user.username
         )
     )]
 async fn upload(
-    State(store): State<SimpleState>,
+    index: Index,
+    Extension(user): Extension<User>,
     TypedMultipart(data): TypedMultipart<RequestData>,
-) {
-    let distribution: Distribution = data.into();
+) -> Result<(), PackageError> {
+    // Reading the spooled archive is blocking, keep it off the async runtime.
+    let distribution = spawn_blocking_with_tracing(move || Distribution::try_from(data))
+        .await
+        .map_err(|_| PackageError::Processing)?
+        .map_err(|e| {
+            tracing::warn!("Rejected upload: {}", e);
+            PackageError::from(e)
+        })?;
+
     tracing::info!("Receive package: {:?}", &distribution.core_metadata.name);
 
-    if (store.upload_package(distribution).await).is_err() {
-        tracing::error!("Failed to upload package");
-    } else {
-        tracing::info!("Package has been added to index");
+    if let Err(e) = index.store.upload_package(distribution, &user).await {
+        tracing::error!("Failed to upload package: {}", e);
+        return Err(e);
     }
+
+    tracing::info!("Package has been added to index");
+
+    Ok(())
+}
+
+// Responses of the simple API are negotiated, caches must be aware of it.
+fn simple_response(format: SimpleFormat, body: impl IntoResponse) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            (header::VARY, "Accept"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// Path parameters are read by name, routes of the named indexes also
+// capture the index.
+#[derive(Deserialize)]
+struct ProjectPath {
+    project: String,
+}
+
+#[derive(Deserialize)]
+struct DistributionPath {
+    project: String,
+    distribution: String,
 }
 
 #[derive(Serialize)]
 struct Dists {
-    dists: Vec<String>,
+    base: String,
+    project: String,
+    dists: Vec<PkgDist>,
 }
 
 #[tracing::instrument(
         name = "Simple::Get distributions list",
-        skip(engine, store, project),
+        skip(engine, index, path),
         fields(
-            project = %project
+            project = %path.project
         )
     )]
 async fn list_dists(
     engine: AppEngine,
-    Path(project): Path<String>,
-    State(store): State<SimpleState>,
-) -> impl IntoResponse {
-    let dists = store.get_dists(&project).await.unwrap();
-    let dists: Vec<String> = dists.iter().map(|d| d.filename.to_owned()).collect();
+    format: SimpleFormat,
+    Path(path): Path<ProjectPath>,
+    index: Index,
+) -> Result<Response, PackageError> {
+    let project = path.project;
+    let dists = index.store.get_dists(&project).await?;
+
+    if format.is_json() {
+        return Ok(simple_response(
+            format,
+            Json(ProjectDetailJson::new(&index.base, &project, &dists)),
+        ));
+    }
 
-    RenderHtml("simple/dists.jinja", engine, Dists { dists })
+    let base = index.base;
+    Ok(simple_response(
+        format,
+        RenderHtml(
+            "simple/dists.jinja",
+            engine,
+            Dists {
+                base,
+                project,
+                dists,
+            },
+        ),
+    ))
 }
 
 #[derive(Serialize)]
 struct Projects {
+    base: String,
     projects: Vec<String>,
 }
 
-#[tracing::instrument(name = "Simple::List package", skip(engine, store))]
-async fn list_packages(engine: AppEngine, State(store): State<SimpleState>) -> impl IntoResponse {
-    let projects = store.get_projects().await.unwrap();
+#[tracing::instrument(name = "Simple::List package", skip(engine, index))]
+async fn list_packages(
+    engine: AppEngine,
+    format: SimpleFormat,
+    index: Index,
+) -> Result<Response, PackageError> {
+    let projects = index.store.get_projects().await?;
+
+    if format.is_json() {
+        return Ok(simple_response(
+            format,
+            Json(ProjectListJson::new(&projects)),
+        ));
+    }
+
     let projects: Vec<String> = projects.iter().map(|p| p.name.to_owned()).collect();
 
-    RenderHtml("simple/packages.jinja", engine, Projects { projects })
+    Ok(simple_response(
+        format,
+        RenderHtml(
+            "simple/packages.jinja",
+            engine,
+            Projects {
+                base: index.base,
+                projects,
+            },
+        ),
+    ))
+}
+
+fn attachment_headers(filename: &str) -> [(header::HeaderName, String); 2] {
+    [
+        (header::CONTENT_TYPE, String::from("application/octet-stream")),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ),
+    ]
 }
 
 #[tracing::instrument(
-        name = "Simple::Get distributions list",
-        skip(store, project, distribution),
+        name = "Simple::Download a distribution",
+        skip(index, headers, path),
         fields(
-            project = %project,
-            distribution = %distribution
+            project = %path.project,
+            distribution = %path.distribution
         )
     )]
 async fn download_package(
-    State(store): State<SimpleState>,
-    Path((project, distribution)): Path<(String, String)>,
-) -> impl IntoResponse {
-    let file = store.get_dist_file(&project, &distribution).await;
+    index: Index,
+    method: Method,
+    headers: HeaderMap,
+    Path(path): Path<DistributionPath>,
+) -> Result<Response, PackageError> {
+    let DistributionPath {
+        project,
+        distribution,
+    } = path;
+    let store = index.store;
 
-    match file {
-        Ok(file) => {
-            let content = file.content;
-            let filename = file.filename.as_str();
+    // PEP 658 - Core metadata files are served next to their distribution,
+    // as plain text.
+    if let Some(dist) = distribution.strip_suffix(".metadata") {
+        let file = store.get_dist_metadata(&project, dist).await?;
+        return Ok((
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            file.content,
+        )
+            .into_response());
+    }
 
-            let body = content;
+    let file = store.get_dist_file(&project, &distribution).await?;
 
-            let content_type = String::from("octet/stream; charset=utf-8");
-            let content_disposition = format!("attachment; filename=\"{}\"", filename);
+    let validators = [
+        (header::ETAG, download::etag(&file)),
+        (header::LAST_MODIFIED, download::last_modified(&file)),
+    ];
+
+    if download::is_not_modified(&headers, &file) {
+        return Ok((StatusCode::NOT_MODIFIED, validators).into_response());
+    }
 
-            let headers = axum::response::AppendHeaders([
-                (header::CONTENT_TYPE, content_type),
-                (header::CONTENT_DISPOSITION, content_disposition),
-            ]);
+    // The object storage serves the file itself when it can sign URLs.
+    if let Some(url) = store.get_dist_url(&file).await? {
+        return Ok((
+            StatusCode::FOUND,
+            [
+                (header::LOCATION, url),
+                (header::CACHE_CONTROL, String::from("no-store")),
+            ],
+        )
+            .into_response());
+    }
 
-            Ok((headers, body))
+    let range = match download::requested_range(&headers, &file) {
+        Ok(range) => range,
+        Err(_) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", file.size))],
+            )
+                .into_response())
         }
-        Err(_) => Err((StatusCode::NOT_FOUND, "File not found !")),
+    };
+
+    let (status, length, content_range) = match &range {
+        Some(range) => (
+            StatusCode::PARTIAL_CONTENT,
+            range.len(),
+            Some(format!(
+                "bytes {}-{}/{}",
+                range.start,
+                range.end - 1,
+                file.size
+            )),
+        ),
+        None => (StatusCode::OK, file.size, None),
+    };
+
+    // HEAD only needs the headers, the object isn't read.
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        Body::from_stream(store.stream_dist_file(&file, range).await?)
+    };
+
+    let mut response = (
+        status,
+        attachment_headers(&file.filename),
+        validators,
+        [
+            (header::ACCEPT_RANGES, String::from("bytes")),
+            (header::CONTENT_LENGTH, length.to_string()),
+        ],
+        body,
+    )
+        .into_response();
+
+    if let Some(content_range) = content_range {
+        response.headers_mut().insert(
+            header::CONTENT_RANGE,
+            content_range.parse().expect("Valid Content-Range header"),
+        );
     }
+
+    Ok(response)
 }
@@ -1,8 +1,28 @@
-use axum::body::Bytes;
-use axum_typed_multipart::{FieldData, TryFromMultipart};
+use std::collections::HashMap;
+
+use axum::{async_trait, body::Bytes};
+use axum_typed_multipart::{
+    FieldData, FieldMetadata, TryFromChunks, TryFromMultipart, TypedMultipartError,
+};
+use futures::{Stream, StreamExt};
 use serde::Serialize;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+use super::archive::{self, ArchiveError};
+use super::hashes;
+use super::metadata::{self, MetadataError};
+use super::package::{
+    normalize_project_name, CoreMetadata, DistHashes, Distribution, DistributionError, PackageType,
+    UploadedFile,
+};
+use super::requirement::Requirement;
+use super::simple_api::{PkgDist, ProjectName};
+use super::version::Version;
+use super::wheel::WheelFilename;
 
-use super::package::{CoreMetadata, DistHashes, Distribution, File};
+// Version of the simple repository API served by this index (PEP 629, PEP 700).
+pub const SIMPLE_API_VERSION: &str = "1.1";
 
 #[derive(Serialize)]
 pub struct SimpleIndex {
@@ -14,6 +34,131 @@ pub struct ProjectDists {
     pub dists: Vec<String>,
 }
 
+#[derive(Serialize)]
+pub struct SimpleMeta {
+    #[serde(rename = "api-version")]
+    pub api_version: &'static str,
+}
+
+impl Default for SimpleMeta {
+    fn default() -> Self {
+        SimpleMeta {
+            api_version: SIMPLE_API_VERSION,
+        }
+    }
+}
+
+// PEP 691 - Project list
+#[derive(Serialize)]
+pub struct ProjectListJson {
+    pub meta: SimpleMeta,
+    pub projects: Vec<ProjectListEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectListEntry {
+    pub name: String,
+}
+
+// PEP 691 - Project detail
+#[derive(Serialize)]
+pub struct ProjectDetailJson {
+    pub meta: SimpleMeta,
+    pub name: String,
+    pub versions: Vec<String>,
+    pub files: Vec<ProjectFileJson>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectFileJson {
+    pub filename: String,
+    pub url: String,
+    pub hashes: HashMap<String, String>,
+    #[serde(rename = "requires-python", skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<String>,
+    pub size: i64,
+    // PEP 714 - `core-metadata` replaces `dist-info-metadata`, both are served
+    // for clients that only know about PEP 658.
+    #[serde(rename = "core-metadata", skip_serializing_if = "Option::is_none")]
+    pub core_metadata: Option<HashMap<String, String>>,
+    #[serde(rename = "dist-info-metadata", skip_serializing_if = "Option::is_none")]
+    pub dist_info_metadata: Option<HashMap<String, String>>,
+    pub yanked: Yanked,
+}
+
+// PEP 592 - `yanked` is either a boolean or the reason of the yank.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Yanked {
+    Flag(bool),
+    Reason(String),
+}
+
+impl Yanked {
+    pub fn new(yanked: bool, reason: Option<&str>) -> Self {
+        match reason.filter(|r| !r.is_empty()) {
+            Some(reason) if yanked => Yanked::Reason(reason.to_string()),
+            _ => Yanked::Flag(yanked),
+        }
+    }
+}
+
+impl ProjectListJson {
+    pub fn new(projects: &[ProjectName]) -> Self {
+        let projects = projects
+            .iter()
+            .map(|p| ProjectListEntry {
+                name: p.name.to_owned(),
+            })
+            .collect();
+
+        ProjectListJson {
+            meta: SimpleMeta::default(),
+            projects,
+        }
+    }
+}
+
+impl ProjectDetailJson {
+    // Files are linked under `base`, the path of the simple API of the index.
+    pub fn new(base: &str, project: &str, dists: &[PkgDist]) -> Self {
+        let mut versions: Vec<String> = Vec::new();
+        for dist in dists {
+            if !versions.contains(&dist.version) {
+                versions.push(dist.version.to_owned());
+            }
+        }
+
+        let files = dists
+            .iter()
+            .map(|d| {
+                let metadata_hashes = d
+                    .metadata_sha256_digest
+                    .as_ref()
+                    .map(|digest| HashMap::from([("sha256".to_string(), digest.to_owned())]));
+
+                ProjectFileJson {
+                    filename: d.filename.to_owned(),
+                    url: format!("{}/{}/{}", base, project, d.filename),
+                    hashes: HashMap::from([("sha256".to_string(), d.sha256_digest.to_owned())]),
+                    requires_python: d.requires_python.to_owned(),
+                    size: d.size.unwrap_or_default(),
+                    core_metadata: metadata_hashes.clone(),
+                    dist_info_metadata: metadata_hashes,
+                    yanked: Yanked::new(d.yanked, d.yanked_reason.as_deref()),
+                }
+            })
+            .collect();
+
+        ProjectDetailJson {
+            meta: SimpleMeta::default(),
+            name: normalize_project_name(project),
+            versions,
+            files,
+        }
+    }
+}
+
 #[derive(TryFromMultipart)]
 pub struct RequestData {
     #[form_data(field_name = ":action")]
@@ -29,7 +174,7 @@ pub struct RequestData {
     pub pyversion: Option<String>,
 
     // additional meta-data
-    pub metadata_version: String,
+    pub metadata_version: Option<String>,
     pub summary: Option<String>,
     pub home_page: Option<String>,
     pub author: Option<String>,
@@ -45,9 +190,9 @@ pub struct RequestData {
     pub platforms: Vec<String>,
     pub supported_platform: Option<String>,
     // pub comment: Option<String>,
-    pub md5_digest: String,
-    pub sha256_digest: String,
-    pub blake2_256_digest: String,
+    pub md5_digest: Option<String>,
+    pub sha256_digest: Option<String>,
+    pub blake2_256_digest: Option<String>,
     pub description_content_type: Option<String>,
 
     // PEP 314
@@ -57,72 +202,198 @@ pub struct RequestData {
     pub provides_extra: Vec<String>,
 
     // Metadata 1.2
-    pub project_urls: Option<String>,
-    pub provides_dist: Option<String>,
-    pub obsoletes_dist: Option<String>,
-    pub requires_dist: Option<String>,
-    pub requires_external: Option<String>,
+    pub project_urls: Vec<String>,
+    pub provides_dist: Vec<String>,
+    pub obsoletes_dist: Vec<String>,
+    pub requires_dist: Vec<String>,
+    pub requires_external: Vec<String>,
     pub requires_python: Option<String>,
 
-    #[form_data(limit = "unlimited")]
-    pub content: FieldData<Bytes>,
+    // Checked while the file is spooled, larger uploads are rejected with a
+    // 413 before being fully received.
+    #[form_data(limit = "4GiB")]
+    pub content: FieldData<SpooledFile>,
 }
 
-// Traits impl
-impl From<RequestData> for Distribution {
-    fn from(val: RequestData) -> Self {
-        fn parse_string(s: Option<String>) -> Vec<String> {
+// Content of the uploaded file, written to a temporary file and hashed chunk
+// by chunk as it is received.
+pub struct SpooledFile {
+    pub file: NamedTempFile,
+    pub size: u64,
+    pub hashes: DistHashes,
+}
+
+#[async_trait]
+impl TryFromChunks for SpooledFile {
+    async fn try_from_chunks(
+        mut chunks: impl Stream<Item = Result<Bytes, TypedMultipartError>> + Send + Sync + Unpin,
+        _: FieldMetadata,
+    ) -> Result<Self, TypedMultipartError> {
+        let file = NamedTempFile::new().map_err(anyhow::Error::new)?;
+        let mut writer = tokio::fs::File::from_std(file.reopen().map_err(anyhow::Error::new)?);
+
+        let mut hasher = hashes::Hasher::default();
+        let mut size = 0;
+
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            writer.write_all(&chunk).await.map_err(anyhow::Error::new)?;
+        }
+
+        writer.flush().await.map_err(anyhow::Error::new)?;
+
+        let (md5_digest, sha256_digest, blake2_256_digest) = hasher.finalize();
+
+        Ok(SpooledFile {
+            file,
+            size,
+            hashes: DistHashes {
+                md5_digest,
+                sha256_digest,
+                blake2_256_digest,
+            },
+        })
+    }
+}
+
+impl RequestData {
+    // Metadata sent as form fields, only used for distribution formats that
+    // don't embed a core metadata file.
+    fn to_core_metadata(&self) -> CoreMetadata {
+        fn parse_string(s: Option<&str>) -> Vec<String> {
             match s {
                 Some(elt) => elt.split("\r\n").map(|e| e.to_string()).collect(),
                 _ => Vec::new(),
             }
         }
 
-        let filename = val.content.metadata.file_name.expect("No filename");
-        let content = val.content.contents;
-
-        let core_metadata = CoreMetadata {
-            metadata_version: val.metadata_version,
-            name: val.name,
-            version: val.version,
-            platforms: val.platforms,
-            supported_platforms: parse_string(val.supported_platform),
-            summary: val.summary,
-            description: val.description,
-            description_content_type: val.description_content_type,
-            keywords: parse_string(val.keywords),
-            home_page: val.home_page,
-            download_url: val.download_url,
-            author: val.author,
-            author_email: val.author_email,
-            maintainer: val.maintainer,
-            maintainer_email: val.maintainer_email,
-            license: val.license,
-            classifiers: val.classifiers,
-            requires_dists: parse_string(val.requires_dist),
-            requires_python: val.requires_python,
-            requires_externals: parse_string(val.requires_external),
-            project_urls: parse_string(val.project_urls),
-            provides_extras: val.provides_extra,
-            provides_dists: parse_string(val.provides_dist),
-            obsoletes_dists: parse_string(val.obsoletes_dist),
+        CoreMetadata {
+            metadata_version: self.metadata_version.clone().unwrap_or(String::from("1.0")),
+            name: self.name.clone(),
+            version: self.version.clone(),
+            platforms: self.platforms.clone(),
+            supported_platforms: parse_string(self.supported_platform.as_deref()),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            description_content_type: self.description_content_type.clone(),
+            keywords: parse_string(self.keywords.as_deref()),
+            home_page: self.home_page.clone(),
+            download_url: self.download_url.clone(),
+            author: self.author.clone(),
+            author_email: self.author_email.clone(),
+            maintainer: self.maintainer.clone(),
+            maintainer_email: self.maintainer_email.clone(),
+            license: self.license.clone(),
+            classifiers: self.classifiers.clone(),
+            requires_dists: self.requires_dist.clone(),
+            requires_python: self.requires_python.clone(),
+            requires_externals: self.requires_external.clone(),
+            project_urls: self.project_urls.clone(),
+            provides_extras: self.provides_extra.clone(),
+            provides_dists: self.provides_dist.clone(),
+            obsoletes_dists: self.obsoletes_dist.clone(),
+        }
+    }
+}
+
+// Traits impl
+impl TryFrom<RequestData> for Distribution {
+    type Error = DistributionError;
+
+    fn try_from(val: RequestData) -> Result<Self, Self::Error> {
+        // Built before the file is taken out of the request, it is only used
+        // when the distribution doesn't embed a metadata file.
+        let form_metadata = val.to_core_metadata();
+
+        let filename = val
+            .content
+            .metadata
+            .file_name
+            .clone()
+            .ok_or(DistributionError::MissingFilename)?;
+        let SpooledFile {
+            file: content,
+            size,
+            hashes,
+        } = val.content.contents;
+
+        let hashes = hashes.verify(
+            val.md5_digest.as_deref(),
+            val.sha256_digest.as_deref(),
+            val.blake2_256_digest.as_deref(),
+        )?;
+
+        let package_type = match val.filetype.as_deref() {
+            Some(filetype) => PackageType::from_filetype(filetype),
+            None => PackageType::from_filename(&filename),
+        }
+        .ok_or_else(|| DistributionError::UnknownFiletype(val.filetype.clone()))?;
+
+        let file = UploadedFile {
+            filename,
+            content,
+            size,
         };
+        file.check_extension(package_type)?;
 
-        let file = File { filename, content };
+        let python_version = val.pyversion.clone();
 
-        let hashes = DistHashes {
-            md5_digest: val.md5_digest,
-            sha256_digest: val.sha256_digest,
-            blake2_256_digest: val.blake2_256_digest,
+        // Archives are read through a new handle on the spooled file.
+        let open = || {
+            file.content
+                .reopen()
+                .map_err(|e| MetadataError::from(ArchiveError::from(e)))
         };
 
-        let python_version = val.pyversion;
+        // The metadata embedded in the distribution is authoritative, the form
+        // fields sent by the client are only checked against it.
+        let (core_metadata, metadata_file) =
+            match archive::extract_metadata_file(package_type, &file.filename, open()?) {
+                Ok(raw) => {
+                    let core_metadata = metadata::parse_metadata(&String::from_utf8_lossy(&raw))?;
+                    metadata::check_form_metadata(&core_metadata, &val.name, &val.version)?;
+
+                    (core_metadata, Some(Bytes::from(raw)))
+                }
+                Err(ArchiveError::UnsupportedFormat(_)) => (form_metadata, None),
+                Err(e) => return Err(MetadataError::from(e).into()),
+            };
+
+        core_metadata.version.parse::<Version>()?;
 
-        Distribution {
+        for requirement in &core_metadata.requires_dists {
+            requirement.parse::<Requirement>()?;
+        }
+
+        if package_type == PackageType::Sdist {
+            file.check_sdist_name(&core_metadata.name, &core_metadata.version)?;
+        }
+
+        let wheel = match package_type {
+            PackageType::BdistWheel => {
+                let wheel: WheelFilename = file.filename.parse()?;
+                wheel.check_metadata(&core_metadata.name, &core_metadata.version)?;
+                wheel.check_platform_tags()?;
+
+                let wheel_file = archive::extract_wheel_file(&file.filename, open()?)
+                    .map_err(MetadataError::from)?;
+                wheel.check_wheel_file(&String::from_utf8_lossy(&wheel_file))?;
+
+                Some(wheel)
+            }
+            _ => None,
+        };
+
+        Ok(Distribution {
             core_metadata,
+            package_type,
             file,
             hashes,
             python_version,
-        }
+            metadata_file,
+            wheel,
+        })
     }
 }
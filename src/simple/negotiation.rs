@@ -0,0 +1,146 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use hyper::{header, StatusCode};
+use serde::Deserialize;
+
+pub const PYPI_SIMPLE_V1_JSON: &str = "application/vnd.pypi.simple.v1+json";
+pub const PYPI_SIMPLE_V1_HTML: &str = "application/vnd.pypi.simple.v1+html";
+pub const PYPI_SIMPLE_LATEST_JSON: &str = "application/vnd.pypi.simple.latest+json";
+pub const PYPI_SIMPLE_LATEST_HTML: &str = "application/vnd.pypi.simple.latest+html";
+pub const TEXT_HTML: &str = "text/html";
+
+// PEP 691 - JSON-based Simple API for Python Package Indexes
+// https://peps.python.org/pep-0691/
+//
+// The response format is selected from the `Accept` header, or from the
+// `?format=` query parameter for clients that are unable to set headers.
+// When the client does not express any preference, the historical HTML
+// format is served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimpleFormat {
+    Html,
+    PypiHtml,
+    PypiJson,
+}
+
+impl SimpleFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SimpleFormat::Html => TEXT_HTML,
+            SimpleFormat::PypiHtml => PYPI_SIMPLE_V1_HTML,
+            SimpleFormat::PypiJson => PYPI_SIMPLE_V1_JSON,
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        matches!(self, SimpleFormat::PypiJson)
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            PYPI_SIMPLE_V1_JSON | PYPI_SIMPLE_LATEST_JSON => Some(SimpleFormat::PypiJson),
+            PYPI_SIMPLE_V1_HTML | PYPI_SIMPLE_LATEST_HTML => Some(SimpleFormat::PypiHtml),
+            TEXT_HTML => Some(SimpleFormat::Html),
+            _ => None,
+        }
+    }
+
+    // Pick the preferred supported format from an `Accept` header value.
+    // Entries are sorted by quality value, ties keep the order of the header.
+    // Wildcards resolve to the default HTML format.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, &str)> = accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut params = entry.split(';').map(str::trim);
+                let media_type = params.next().filter(|m| !m.is_empty())?;
+
+                let quality = params
+                    .filter_map(|p| p.strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                Some((quality, media_type))
+            })
+            .filter(|(quality, _)| *quality > 0.0)
+            .collect();
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        candidates
+            .into_iter()
+            .find_map(|(_, media_type)| match media_type {
+                "*/*" | "text/*" => Some(SimpleFormat::Html),
+                "application/*" => Some(SimpleFormat::PypiJson),
+                m => Self::from_media_type(&m.to_lowercase()),
+            })
+    }
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SimpleFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query_format = Query::<FormatQuery>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(q)| q.format);
+
+        let accept = match query_format {
+            Some(format) => Some(format),
+            None => parts
+                .headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+
+        match accept {
+            None => Ok(SimpleFormat::Html),
+            Some(accept) if accept.trim().is_empty() => Ok(SimpleFormat::Html),
+            Some(accept) => SimpleFormat::from_accept(&accept).ok_or((
+                StatusCode::NOT_ACCEPTABLE,
+                "Unsupported content type. Supported types are application/vnd.pypi.simple.v1+json, application/vnd.pypi.simple.v1+html and text/html.",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SimpleFormat;
+
+    #[test]
+    fn accept_prefers_highest_quality() {
+        let accept = "application/vnd.pypi.simple.v1+json, application/vnd.pypi.simple.v1+html;q=0.2, text/html;q=0.01";
+        assert_eq!(SimpleFormat::from_accept(accept), Some(SimpleFormat::PypiJson));
+
+        let accept = "application/vnd.pypi.simple.v1+json;q=0.1, text/html";
+        assert_eq!(SimpleFormat::from_accept(accept), Some(SimpleFormat::Html));
+    }
+
+    #[test]
+    fn accept_wildcard_defaults_to_html() {
+        assert_eq!(SimpleFormat::from_accept("*/*"), Some(SimpleFormat::Html));
+    }
+
+    #[test]
+    fn accept_unsupported_type_is_rejected() {
+        assert_eq!(SimpleFormat::from_accept("application/xml"), None);
+        assert_eq!(
+            SimpleFormat::from_accept("application/vnd.pypi.simple.v1+json;q=0"),
+            None
+        );
+    }
+}
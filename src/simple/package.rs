@@ -1,7 +1,16 @@
+use std::time::SystemTime;
+
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
 use validator::{Validate, ValidationError};
 
+use super::hashes::{verify_digest, DigestError};
+use super::metadata::MetadataError;
+use super::requirement::InvalidRequirement;
+use super::version::{same_version, InvalidVersion, Version};
+use super::wheel::{WheelError, WheelFilename};
+
 #[derive(Serialize, Validate, Deserialize)]
 pub struct CoreMetadata {
     // - Metadata-Version
@@ -314,6 +323,7 @@ pub struct CoreMetadata {
     pub obsoletes_dists: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PackageType {
     BdistDmg,
     BdistDumb,
@@ -326,6 +336,53 @@ pub enum PackageType {
 }
 
 impl PackageType {
+    // Map the `filetype` field sent by twine and setuptools.
+    pub fn from_filetype(filetype: &str) -> Option<Self> {
+        match filetype {
+            "bdist_dmg" => Some(PackageType::BdistDmg),
+            "bdist_dumb" => Some(PackageType::BdistDumb),
+            "bdist_egg" => Some(PackageType::BdistEgg),
+            "bdist_msi" => Some(PackageType::BdistMsi),
+            "bdist_rpm" => Some(PackageType::BdistRpm),
+            "bdist_wheel" => Some(PackageType::BdistWheel),
+            "bdist_wininst" => Some(PackageType::BdistWininst),
+            "sdist" => Some(PackageType::Sdist),
+            _ => None,
+        }
+    }
+
+    // Guess the package type of clients that don't send a `filetype`.
+    // Only unambiguous extensions are considered.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let filename = filename.to_lowercase();
+
+        [
+            PackageType::BdistWheel,
+            PackageType::Sdist,
+            PackageType::BdistEgg,
+            PackageType::BdistMsi,
+            PackageType::BdistRpm,
+            PackageType::BdistDmg,
+            PackageType::BdistWininst,
+        ]
+        .into_iter()
+        .find(|t| t.extensions().iter().any(|ext| filename.ends_with(ext)))
+    }
+
+    // PEP 625 - Source distributions must be `.tar.gz` archives.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            PackageType::BdistDmg => &[".dmg"],
+            PackageType::BdistDumb => &[".tar.gz", ".zip"],
+            PackageType::BdistEgg => &[".egg"],
+            PackageType::BdistMsi => &[".msi"],
+            PackageType::BdistRpm => &[".rpm"],
+            PackageType::BdistWheel => &[".whl"],
+            PackageType::BdistWininst => &[".exe"],
+            PackageType::Sdist => &[".tar.gz"],
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             PackageType::BdistDmg => "bdist_dmg",
@@ -345,17 +402,166 @@ pub struct File {
     pub content: Bytes,
 }
 
+// Distribution file of an upload, spooled to disk while it is received so
+// large files are never held in memory.
+pub struct UploadedFile {
+    pub filename: String,
+    pub content: NamedTempFile,
+    pub size: u64,
+}
+
+// Stored distribution, its content is streamed on download from the path
+// it was stored at.
+pub struct DistFile {
+    pub path: String,
+    pub filename: String,
+    pub size: usize,
+    pub sha256_digest: String,
+    pub last_modified: SystemTime,
+}
+
 pub struct DistHashes {
     pub md5_digest: String,
     pub sha256_digest: String,
     pub blake2_256_digest: String,
 }
 
+impl DistHashes {
+    // Check the digests computed while receiving the content against the
+    // digests sent by the client.
+    pub fn verify(
+        self,
+        md5_digest: Option<&str>,
+        sha256_digest: Option<&str>,
+        blake2_256_digest: Option<&str>,
+    ) -> Result<DistHashes, DigestError> {
+        Ok(DistHashes {
+            md5_digest: verify_digest("md5", md5_digest, self.md5_digest)?,
+            sha256_digest: verify_digest("sha256", sha256_digest, self.sha256_digest)?,
+            blake2_256_digest: verify_digest(
+                "blake2_256",
+                blake2_256_digest,
+                self.blake2_256_digest,
+            )?,
+        })
+    }
+}
+
 pub struct Distribution {
     pub core_metadata: CoreMetadata,
-    pub file: File,
+    pub package_type: PackageType,
+    pub file: UploadedFile,
     pub hashes: DistHashes,
     pub python_version: Option<String>,
+    // Raw core metadata file (`METADATA` or `PKG-INFO`) found in the distribution.
+    pub metadata_file: Option<Bytes>,
+    pub wheel: Option<WheelFilename>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DistributionError {
+    #[error("The uploaded file has no filename.")]
+    MissingFilename,
+
+    #[error("Unknown or missing filetype {0:?}.")]
+    UnknownFiletype(Option<String>),
+
+    #[error("Invalid filename {filename}: {reason}")]
+    InvalidFilename { filename: String, reason: String },
+
+    #[error(transparent)]
+    Digest(#[from] DigestError),
+
+    #[error(transparent)]
+    Metadata(#[from] MetadataError),
+
+    #[error(transparent)]
+    Wheel(#[from] WheelError),
+
+    #[error(transparent)]
+    Version(#[from] InvalidVersion),
+
+    #[error(transparent)]
+    Requirement(#[from] InvalidRequirement),
+}
+
+impl UploadedFile {
+    // The filename must use one of the extensions of its package type.
+    pub fn check_extension(&self, package_type: PackageType) -> Result<(), DistributionError> {
+        let filename = self.filename.to_lowercase();
+
+        if package_type
+            .extensions()
+            .iter()
+            .any(|ext| filename.ends_with(ext))
+        {
+            Ok(())
+        } else {
+            Err(DistributionError::InvalidFilename {
+                filename: self.filename.to_owned(),
+                reason: format!(
+                    "a {} must use one of the extensions {}.",
+                    package_type.as_str(),
+                    package_type.extensions().join(", ")
+                ),
+            })
+        }
+    }
+
+    // PEP 625 - File name of a Source Distribution
+    // Source distributions are named `{name}-{version}.tar.gz`, where `{name}`
+    // is normalized with underscores so the filename can be split on `-`.
+    pub fn check_sdist_name(&self, name: &str, version: &str) -> Result<(), DistributionError> {
+        let invalid = |reason: String| DistributionError::InvalidFilename {
+            filename: self.filename.to_owned(),
+            reason,
+        };
+
+        let stem = self.filename[..self.filename.len() - ".tar.gz".len()].to_string();
+
+        let Some((dist_name, dist_version)) = stem.split_once('-') else {
+            return Err(invalid(String::from(
+                "source distributions must be named {name}-{version}.tar.gz.",
+            )));
+        };
+
+        if normalize_project_name(dist_name) != normalize_project_name(name) {
+            return Err(invalid(format!(
+                "the name of the source distribution must be {}.",
+                normalize_project_name(name).replace('-', "_")
+            )));
+        }
+
+        if !same_version(dist_version, version) {
+            return Err(invalid(format!(
+                "the version of the source distribution must be {}.",
+                version
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+// PEP 503 - Normalized names
+// Runs of `-`, `_` and `.` are replaced by a single `-` and the name is lowercased.
+pub fn normalize_project_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut previous_is_separator = false;
+
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !previous_is_separator {
+                normalized.push('-');
+            }
+            previous_is_separator = true;
+        } else {
+            normalized.extend(c.to_lowercase());
+            previous_is_separator = false;
+        }
+    }
+
+    normalized
 }
 
 fn pep_503_normalized_name(_name: &str) -> Result<(), ValidationError> {
@@ -363,10 +569,7 @@ fn pep_503_normalized_name(_name: &str) -> Result<(), ValidationError> {
 }
 
 fn pep_440_version_format(version: &str) -> Result<(), ValidationError> {
-    let pep440 = r"";
-    let re = regex::Regex::new(pep440).unwrap();
-
-    if !re.is_match(version) {
+    if version.parse::<Version>().is_err() {
         return Err(ValidationError::new("Version format incorrect."));
     }
 
@@ -383,3 +586,57 @@ fn pep_301_valid_classifier(names: &[String]) -> Result<(), ValidationError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::NamedTempFile;
+
+    use super::{PackageType, UploadedFile};
+
+    fn file(filename: &str) -> UploadedFile {
+        UploadedFile {
+            filename: filename.to_string(),
+            content: NamedTempFile::new().unwrap(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn package_type_is_guessed_from_unambiguous_extensions() {
+        assert_eq!(
+            PackageType::from_filename("nest-0.1.0-py3-none-any.whl"),
+            Some(PackageType::BdistWheel)
+        );
+        assert_eq!(
+            PackageType::from_filename("nest-0.1.0.tar.gz"),
+            Some(PackageType::Sdist)
+        );
+        assert_eq!(PackageType::from_filename("nest-0.1.0.zip"), None);
+    }
+
+    #[test]
+    fn filename_extension_must_match_package_type() {
+        assert!(file("nest-0.1.0.tar.gz")
+            .check_extension(PackageType::BdistWheel)
+            .is_err());
+        assert!(file("nest-0.1.0.zip")
+            .check_extension(PackageType::Sdist)
+            .is_err());
+        assert!(file("nest-0.1.0.zip")
+            .check_extension(PackageType::BdistDumb)
+            .is_ok());
+    }
+
+    #[test]
+    fn sdist_name_follows_pep_625() {
+        assert!(file("nest_index-0.1.0.tar.gz")
+            .check_sdist_name("nest-index", "0.1.0")
+            .is_ok());
+        assert!(file("nest-index-0.1.0.tar.gz")
+            .check_sdist_name("nest-index", "0.1.0")
+            .is_err());
+        assert!(file("nest_index-0.2.0.tar.gz")
+            .check_sdist_name("nest-index", "0.1.0")
+            .is_err());
+    }
+}
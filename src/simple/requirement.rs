@@ -0,0 +1,494 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::package::normalize_project_name;
+use super::version::Version;
+
+// PEP 508 - Dependency specification for Python Software Packages
+// https://packaging.python.org/en/latest/specifications/dependency-specifiers/
+//
+// `name [extras] (specifiers | @ url) ; marker`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    // Normalized name (PEP 503).
+    pub name: String,
+    // Normalized extras, sorted.
+    pub extras: Vec<String>,
+    // Normalized specifier set, e.g. `<3,>=2.0`.
+    pub specifier: Option<String>,
+    pub url: Option<String>,
+    // Normalized environment marker, e.g. `python_version >= "3.8"`.
+    pub marker: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid requirement {requirement}: {reason}")]
+pub struct InvalidRequirement {
+    pub requirement: String,
+    pub reason: String,
+}
+
+impl FromStr for Requirement {
+    type Err = InvalidRequirement;
+
+    fn from_str(requirement: &str) -> Result<Self, Self::Err> {
+        parse_requirement(requirement.trim()).map_err(|reason| InvalidRequirement {
+            requirement: requirement.to_string(),
+            reason,
+        })
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+
+        if let Some(url) = &self.url {
+            write!(f, " @ {}", url)?;
+        } else if let Some(specifier) = &self.specifier {
+            write!(f, "{}", specifier)?;
+        }
+
+        if let Some(marker) = &self.marker {
+            let separator = if self.url.is_some() { " ; " } else { "; " };
+            write!(f, "{}{}", separator, marker)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+// Names must start and end with a letter or a digit.
+fn parse_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name.chars().all(is_name_char)
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric());
+
+    if !valid {
+        return Err(format!("invalid name {:?}.", name));
+    }
+
+    Ok(normalize_project_name(name))
+}
+
+fn parse_requirement(requirement: &str) -> Result<Requirement, String> {
+    let name_end = requirement
+        .find(|c: char| !is_name_char(c))
+        .unwrap_or(requirement.len());
+    let name = parse_name(&requirement[..name_end])?;
+    let mut rest = requirement[name_end..].trim_start();
+
+    let mut extras = Vec::new();
+    if let Some(after) = rest.strip_prefix('[') {
+        let (list, after) = after
+            .split_once(']')
+            .ok_or_else(|| String::from("unclosed extras."))?;
+
+        for extra in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            extras.push(parse_name(extra)?);
+        }
+        extras.sort();
+        extras.dedup();
+
+        rest = after.trim_start();
+    }
+
+    // URL requirements need a space before the marker, `;` is valid in URLs.
+    if let Some(after) = rest.strip_prefix('@') {
+        let after = after.trim_start();
+        let (url, marker) = match after.split_once(char::is_whitespace) {
+            Some((url, marker)) => (url, marker.trim_start()),
+            None => (after, ""),
+        };
+
+        if url.is_empty() || !url.contains(':') {
+            return Err(format!("invalid URL {:?}.", url));
+        }
+
+        let marker = match marker {
+            "" => None,
+            m => match m.strip_prefix(';') {
+                Some(m) => Some(parse_marker(m)?),
+                None => return Err(format!("unexpected {:?} after URL.", m)),
+            },
+        };
+
+        return Ok(Requirement {
+            name,
+            extras,
+            specifier: None,
+            url: Some(url.to_string()),
+            marker,
+        });
+    }
+
+    let (specifier, marker) = match rest.split_once(';') {
+        Some((specifier, marker)) => (specifier, Some(parse_marker(marker)?)),
+        None => (rest, None),
+    };
+
+    // Specifiers may be wrapped in parentheses, e.g. `requests (>=2.0)`.
+    let specifier = specifier.trim();
+    let specifier = match specifier.strip_prefix('(') {
+        Some(inner) => inner
+            .strip_suffix(')')
+            .ok_or_else(|| String::from("unclosed parenthesis."))?,
+        None => specifier,
+    };
+
+    let specifier = match specifier.trim() {
+        "" => None,
+        s => Some(parse_specifier_set(s)?),
+    };
+
+    Ok(Requirement {
+        name,
+        extras,
+        specifier,
+        url: None,
+        marker,
+    })
+}
+
+const OPERATORS: [&str; 8] = ["===", "~=", "==", "!=", "<=", ">=", "<", ">"];
+
+// PEP 440 - Version specifiers, comma separated clauses.
+// Clauses are sorted, so equivalent sets are stored the same way.
+fn parse_specifier_set(specifiers: &str) -> Result<String, String> {
+    let mut clauses = specifiers
+        .split(',')
+        .map(|clause| parse_specifier(clause.trim()))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    clauses.sort();
+    clauses.dedup();
+
+    Ok(clauses.join(","))
+}
+
+fn parse_specifier(clause: &str) -> Result<String, String> {
+    let invalid = || format!("invalid version specifier {:?}.", clause);
+
+    let operator = OPERATORS
+        .iter()
+        .find(|op| clause.starts_with(*op))
+        .ok_or_else(invalid)?;
+    let version = clause[operator.len()..].trim();
+
+    if version.is_empty() || version.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    match *operator {
+        // Arbitrary equality compares strings, any version is allowed.
+        "===" => {}
+        "==" | "!=" => {
+            let (version, wildcard) = match version.strip_suffix(".*") {
+                Some(prefix) => (prefix, true),
+                None => (version, false),
+            };
+            let parsed: Version = version.parse().map_err(|_| invalid())?;
+
+            if wildcard && parsed.is_local() {
+                return Err(invalid());
+            }
+        }
+        "~=" => {
+            let parsed: Version = version.parse().map_err(|_| invalid())?;
+
+            if parsed.release.len() < 2 || parsed.is_local() {
+                return Err(invalid());
+            }
+        }
+        _ => {
+            let parsed: Version = version.parse().map_err(|_| invalid())?;
+
+            if parsed.is_local() {
+                return Err(invalid());
+            }
+        }
+    }
+
+    Ok(format!("{}{}", operator, version))
+}
+
+const MARKER_VARIABLES: [&str; 12] = [
+    "python_version",
+    "python_full_version",
+    "os_name",
+    "sys_platform",
+    "platform_release",
+    "platform_system",
+    "platform_version",
+    "platform_machine",
+    "platform_python_implementation",
+    "implementation_name",
+    "implementation_version",
+    "extra",
+];
+
+// Names accepted by older versions of the specification.
+fn marker_variable(name: &str) -> Option<&'static str> {
+    let name = match name {
+        "os.name" => "os_name",
+        "sys.platform" => "sys_platform",
+        "platform.version" => "platform_version",
+        "platform.machine" => "platform_machine",
+        "platform.python_implementation" | "python_implementation" => {
+            "platform_python_implementation"
+        }
+        name => name,
+    };
+
+    MARKER_VARIABLES.iter().find(|v| **v == name).copied()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Variable(&'static str),
+    String(String),
+    Operator(&'static str),
+    And,
+    Or,
+}
+
+fn tokenize_marker(marker: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = marker.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('(') {
+            tokens.push(Token::Open);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(')') {
+            tokens.push(Token::Close);
+            rest = after;
+        } else if rest.starts_with(['"', '\'']) {
+            let quote = &rest[..1];
+            let (value, after) = rest[1..]
+                .split_once(quote)
+                .ok_or_else(|| String::from("unclosed string in marker."))?;
+            tokens.push(Token::String(value.to_string()));
+            rest = after;
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Operator(op));
+            rest = &rest[op.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.')))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+
+            let token = match word {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "in" => Token::Operator("in"),
+                "not" => {
+                    let after = rest.trim_start();
+                    match after.strip_prefix("in") {
+                        Some(after_in) if !after_in.starts_with(is_name_char) => {
+                            rest = after_in;
+                            Token::Operator("not in")
+                        }
+                        _ => return Err(String::from("expected `in` after `not` in marker.")),
+                    }
+                }
+                "" => return Err(format!("unexpected {:?} in marker.", rest)),
+                word => Token::Variable(
+                    marker_variable(word)
+                        .ok_or_else(|| format!("unknown marker variable {:?}.", word))?,
+                ),
+            };
+            tokens.push(token);
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+// Recursive descent parser over the marker grammar, the marker is rebuilt in
+// its normalized form: double quotes and single spaces between tokens.
+struct MarkerParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl MarkerParser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self) -> Result<String, String> {
+        let mut marker = self.parse_and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            marker = format!("{} or {}", marker, self.parse_and()?);
+        }
+
+        Ok(marker)
+    }
+
+    fn parse_and(&mut self) -> Result<String, String> {
+        let mut marker = self.parse_expression()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            marker = format!("{} and {}", marker, self.parse_expression()?);
+        }
+
+        Ok(marker)
+    }
+
+    fn parse_expression(&mut self) -> Result<String, String> {
+        if self.peek() == Some(&Token::Open) {
+            self.next();
+            let marker = self.parse_or()?;
+
+            return match self.next() {
+                Some(Token::Close) => Ok(format!("({})", marker)),
+                _ => Err(String::from("unclosed parenthesis in marker.")),
+            };
+        }
+
+        let left = self.next();
+        let operator = match self.next() {
+            Some(Token::Operator(op)) => op,
+            _ => return Err(String::from("expected a comparison in marker.")),
+        };
+        let right = self.next();
+
+        let is_extra = matches!(left, Some(Token::Variable("extra")))
+            || matches!(right, Some(Token::Variable("extra")));
+
+        let value = |token: Option<Token>| match token {
+            Some(Token::Variable(v)) => Ok(v.to_string()),
+            // Extras are compared with their normalized names.
+            Some(Token::String(s)) if is_extra => Ok(format!("\"{}\"", normalize_project_name(&s))),
+            Some(Token::String(s)) => Ok(format!("\"{}\"", s)),
+            _ => Err(String::from("expected a variable or a string in marker.")),
+        };
+
+        let (left, right) = (value(left)?, value(right)?);
+
+        if left.starts_with('"') == right.starts_with('"') {
+            return Err(String::from("a marker compares a variable with a string."));
+        }
+
+        Ok(format!("{} {} {}", left, operator, right))
+    }
+}
+
+fn parse_marker(marker: &str) -> Result<String, String> {
+    let mut parser = MarkerParser {
+        tokens: tokenize_marker(marker)?,
+        position: 0,
+    };
+
+    if parser.tokens.is_empty() {
+        return Err(String::from("empty marker."));
+    }
+
+    let marker = parser.parse_or()?;
+
+    if parser.position != parser.tokens.len() {
+        return Err(String::from("unexpected trailing tokens in marker."));
+    }
+
+    Ok(marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Requirement;
+
+    fn req(requirement: &str) -> Requirement {
+        requirement.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_name_extras_and_specifiers() {
+        let requirement = req("Requests[Socks, security] (>=2.0, <3)");
+
+        assert_eq!(requirement.name, "requests");
+        assert_eq!(requirement.extras, vec!["security", "socks"]);
+        assert_eq!(requirement.specifier.as_deref(), Some("<3,>=2.0"));
+        assert_eq!(requirement.marker, None);
+        assert_eq!(requirement.to_string(), "requests[security,socks]<3,>=2.0");
+    }
+
+    #[test]
+    fn parse_and_normalize_markers() {
+        let requirement =
+            req("pytest>=7; (python_version>='3.8' and os.name == 'posix') or extra=='Test_All'");
+
+        assert_eq!(
+            requirement.marker.as_deref(),
+            Some(r#"(python_version >= "3.8" and os_name == "posix") or extra == "test-all""#)
+        );
+
+        let requirement =
+            req("pywin32 ; 'win' in sys_platform and platform_machine not in 'arm64'");
+        assert_eq!(
+            requirement.marker.as_deref(),
+            Some(r#""win" in sys_platform and platform_machine not in "arm64""#)
+        );
+    }
+
+    #[test]
+    fn parse_url_requirements() {
+        let requirement = req("pip @ https://github.com/pypa/pip/archive/1.3.1.zip#sha1=da9234ee ; python_version < '3.12'");
+
+        assert_eq!(
+            requirement.url.as_deref(),
+            Some("https://github.com/pypa/pip/archive/1.3.1.zip#sha1=da9234ee")
+        );
+        assert_eq!(requirement.specifier, None);
+        assert_eq!(
+            requirement.marker.as_deref(),
+            Some(r#"python_version < "3.12""#)
+        );
+    }
+
+    #[test]
+    fn reject_invalid_requirements() {
+        for requirement in [
+            "",
+            "-requests",
+            "requests[socks",
+            "requests >= latest",
+            "requests ~= 2",
+            "requests == 2.0 3.0",
+            "requests; python_version >= 3.8",
+            "requests; unknown_variable == 'x'",
+            "requests; python_version >= '3.8' and",
+            "requests @ not-a-url",
+        ] {
+            assert!(
+                requirement.parse::<Requirement>().is_err(),
+                "{:?} should be rejected",
+                requirement
+            );
+        }
+    }
+}
@@ -1,14 +1,115 @@
+use std::ops::Range;
 use std::sync::Arc;
 
+use super::index::IndexDefinition;
 use super::package;
+use super::upstream;
+use crate::authentication::User;
 
 use anyhow::Result;
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use hyper::{ext::ReasonPhrase, StatusCode};
 use serde::{Deserialize, Serialize};
 
 use async_trait::async_trait;
 
-#[derive(Debug)]
-pub struct PackageError;
+#[derive(Debug, thiserror::Error)]
+pub enum PackageError {
+    #[error(transparent)]
+    InvalidDistribution(#[from] package::DistributionError),
+
+    #[error("File already exists ({0}).")]
+    FileExists(String),
+
+    #[error("The user isn't allowed to upload to project {0}.")]
+    PermissionDenied(String),
+
+    #[error("Only the owner of project {0} can change its maintainers.")]
+    NotOwner(String),
+
+    #[error("Invalid maintainer change: {0}.")]
+    InvalidMaintainer(String),
+
+    #[error("Project {0} exists on the upstream index, an administrator must claim it before uploading.")]
+    UpstreamConflict(String),
+
+    #[error("Invalid index definition: {0}.")]
+    InvalidIndex(String),
+
+    #[error("Release already exists ({0}).")]
+    ReleaseExists(String),
+
+    #[error("Not found.")]
+    NotFound,
+
+    #[error("Unable to process the uploaded file.")]
+    Processing,
+
+    #[error(transparent)]
+    Storage(#[from] object_store::Error),
+
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+
+    #[error(transparent)]
+    Upstream(#[from] upstream::UpstreamError),
+}
+
+impl PackageError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PackageError::InvalidDistribution(_) => StatusCode::BAD_REQUEST,
+            // twine `--skip-existing` recognizes conflicts.
+            PackageError::FileExists(_) => StatusCode::CONFLICT,
+            PackageError::InvalidIndex(_)
+            | PackageError::ReleaseExists(_)
+            | PackageError::InvalidMaintainer(_) => StatusCode::BAD_REQUEST,
+            PackageError::PermissionDenied(_)
+            | PackageError::NotOwner(_)
+            | PackageError::UpstreamConflict(_) => StatusCode::FORBIDDEN,
+            PackageError::NotFound => StatusCode::NOT_FOUND,
+            PackageError::Processing | PackageError::Storage(_) | PackageError::Database(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            PackageError::Upstream(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+// Upload clients (twine) show the reason phrase of failed responses to the
+// user, the message is sent both as reason phrase and as body like PyPI does.
+// Storage, database and upstream errors are only logged, their details stay private.
+impl IntoResponse for PackageError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+
+        let message = match &self {
+            PackageError::Storage(e) => {
+                tracing::error!("Storage failure: {}", e);
+                String::from("Unable to store the distribution, try again later.")
+            }
+            PackageError::Database(e) => {
+                tracing::error!("Database failure: {}", e);
+                String::from("Unable to register the distribution, try again later.")
+            }
+            PackageError::Upstream(e) => {
+                tracing::error!("Upstream index failure: {}", e);
+                String::from("Unable to fetch the distribution from the upstream index.")
+            }
+            e => e.to_string(),
+        };
+
+        let mut response = (status, message.clone()).into_response();
+
+        if let Ok(reason) = ReasonPhrase::try_from(message.replace(['\r', '\n'], " ")) {
+            response.extensions_mut().insert(reason);
+        }
+
+        response
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct ProjectName {
@@ -19,21 +120,169 @@ pub struct ProjectName {
 pub struct PkgDist {
     pub filename: String,
     pub path: String,
+    pub version: String,
+    pub size: Option<i64>,
+    pub sha256_digest: String,
+    pub requires_python: Option<String>,
+    pub metadata_sha256_digest: Option<String>,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+// Where the files of a project come from. Internal projects never look at the
+// upstream index, so a public project can't shadow them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "upstream_policy", rename_all = "snake_case")]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamPolicy {
+    // Only the uploaded files.
+    Internal,
+    // Only the files of the upstream index, uploads are rejected.
+    Upstream,
+    // Both, uploaded files take precedence over upstream files of the same name.
+    Merged,
+}
+
+// Accounts allowed to upload to a project, the owner also manages the
+// maintainers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "project_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectRole {
+    Owner,
+    Maintainer,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Maintainer {
+    pub username: String,
+    pub role: ProjectRole,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+// A release copied from an index to another one, kept as an audit record.
+#[derive(Serialize, Deserialize)]
+pub struct Promotion {
+    pub project: String,
+    pub version: String,
+    pub source: String,
+    pub target: String,
+    pub filenames: Vec<String>,
+    pub promoted_by: String,
+    pub promoted_at: String,
 }
 
 #[async_trait]
 pub trait SimpleStore: Send + Sync + 'static {
-    async fn upload_package(&self, distribution: package::Distribution)
-        -> Result<(), PackageError>;
+    // Only the owner and the maintainers of the project upload to it, the
+    // uploader of a new project becomes its owner. Admins upload to every
+    // project.
+    async fn upload_package(
+        &self,
+        distribution: package::Distribution,
+        user: &User,
+    ) -> Result<(), PackageError>;
     async fn get_projects(&self) -> Result<Vec<ProjectName>, PackageError>;
+    async fn get_releases(&self, project: &str) -> Result<Vec<Release>, PackageError>;
     async fn get_dists(&self, project: &str) -> Result<Vec<PkgDist>, PackageError>;
-    async fn get_dist_file(&self, project: &str, dist: &str)
-        -> Result<package::File, PackageError>;
+    async fn get_dist_file(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<package::DistFile, PackageError>;
+    // Content of the file, or only the given byte range of it.
+    async fn stream_dist_file(
+        &self,
+        file: &package::DistFile,
+        range: Option<Range<usize>>,
+    ) -> Result<BoxStream<'static, Result<Bytes, PackageError>>, PackageError>;
+    // Presigned URL of the file, `None` when downloads go through the index.
+    async fn get_dist_url(&self, file: &package::DistFile) -> Result<Option<String>, PackageError>;
     async fn get_dist_metadata(
         &self,
         project: &str,
         dist: &str,
-    ) -> Result<package::CoreMetadata, PackageError>;
+    ) -> Result<package::File, PackageError>;
+    // PEP 592 - Yank (or unyank with `yanked = false`) a whole release.
+    async fn yank_release(
+        &self,
+        project: &str,
+        version: &str,
+        yanked: bool,
+        reason: Option<&str>,
+        user: &User,
+    ) -> Result<(), PackageError>;
+    // PEP 592 - Yank (or unyank with `yanked = false`) a single file.
+    async fn yank_file(
+        &self,
+        project: &str,
+        filename: &str,
+        yanked: bool,
+        reason: Option<&str>,
+        user: &User,
+    ) -> Result<(), PackageError>;
+    // Files are immutable unless the project allows overwrites.
+    async fn get_overwrite_policy(&self, project: &str) -> Result<bool, PackageError>;
+    async fn set_overwrite_policy(&self, project: &str, allow: bool) -> Result<(), PackageError>;
+    async fn get_upstream_policy(&self, project: &str) -> Result<UpstreamPolicy, PackageError>;
+    // Claims the project when it isn't known yet, uploads are then accepted
+    // unless the policy is `upstream`.
+    async fn set_upstream_policy(
+        &self,
+        project: &str,
+        policy: UpstreamPolicy,
+    ) -> Result<(), PackageError>;
+    // Store of the named index, it serves its own projects and the ones of
+    // its parents.
+    async fn get_index(&self, name: &str) -> Result<SimpleState, PackageError>;
+    async fn get_index_definition(&self, name: &str) -> Result<IndexDefinition, PackageError>;
+    // Creates the index when it doesn't exist yet.
+    async fn set_index_definition(
+        &self,
+        name: &str,
+        definition: &IndexDefinition,
+    ) -> Result<(), PackageError>;
+    // Copies the release and its files, unchanged, to the same project of the
+    // target index, the source index keeps serving them. The user must maintain the project in both indexes, a
+    // project created by the promotion gets the maintainers of the source.
+    async fn promote_release(
+        &self,
+        project: &str,
+        version: &str,
+        target: &str,
+        user: &User,
+    ) -> Result<Promotion, PackageError>;
+    // Promotions of the project from or to the index, newest first.
+    async fn get_promotions(&self, project: &str) -> Result<Vec<Promotion>, PackageError>;
+    // The owner first, then the maintainers by name.
+    async fn get_maintainers(&self, project: &str) -> Result<Vec<Maintainer>, PackageError>;
+    // The maintainers are changed by the owner of the project or by an admin,
+    // maintainers can also remove themselves.
+    async fn add_maintainer(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError>;
+    async fn remove_maintainer(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError>;
+    // The previous owner stays a maintainer.
+    async fn transfer_ownership(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError>;
 }
 
 pub type SimpleState = Arc<dyn SimpleStore>;
@@ -1,15 +1,32 @@
+use super::hashes;
+use super::index::{IndexDefinition, IndexRule, ProjectOverride, ROOT_INDEX, ROOT_INDEX_ID};
 use super::package;
-use super::simple_api::{PackageError, PkgDist, ProjectName, SimpleStore};
+use super::requirement::Requirement;
+use super::simple_api::{
+    Maintainer, PackageError, PkgDist, ProjectName, ProjectRole, Promotion, Release, SimpleState,
+    SimpleStore, UpstreamPolicy,
+};
+use super::upstream::{Upstream, UpstreamContent, UpstreamFile};
+use super::version::Version;
+
+use crate::authentication::User;
 
 use anyhow::Result;
+use axum_login::AuthUser;
 use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
 use pulldown_cmark::{html, Parser};
-use regex::Regex;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use object_store::{path::Path, ObjectStore};
+use object_store::{path::Path, signer::Signer, ObjectStore};
 
 #[derive(sqlx::Type)]
 #[sqlx(type_name = "packagetype")]
@@ -25,6 +42,21 @@ enum PackageType {
     Sdist,
 }
 
+impl From<package::PackageType> for PackageType {
+    fn from(package_type: package::PackageType) -> Self {
+        match package_type {
+            package::PackageType::BdistDmg => PackageType::BdistDmg,
+            package::PackageType::BdistDumb => PackageType::BdistDumb,
+            package::PackageType::BdistEgg => PackageType::BdistEgg,
+            package::PackageType::BdistMsi => PackageType::BdistMsi,
+            package::PackageType::BdistRpm => PackageType::BdistRpm,
+            package::PackageType::BdistWheel => PackageType::BdistWheel,
+            package::PackageType::BdistWininst => PackageType::BdistWininst,
+            package::PackageType::Sdist => PackageType::Sdist,
+        }
+    }
+}
+
 #[derive(sqlx::Type)]
 #[sqlx(type_name = "dependency_kind")]
 #[sqlx(rename_all = "snake_case")]
@@ -44,423 +76,2431 @@ impl sqlx::postgres::PgHasArrayType for DependencyKind {
     }
 }
 
+#[derive(Debug, sqlx::Type)]
+#[sqlx(type_name = "index_rule")]
+#[sqlx(rename_all = "snake_case")]
+enum RuleKind {
+    Pin,
+    Merge,
+    Block,
+}
+
+impl sqlx::postgres::PgHasArrayType for RuleKind {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_index_rule")
+    }
+}
+
+fn index_rule(rule: RuleKind, pinned_index: Option<String>) -> IndexRule {
+    match rule {
+        RuleKind::Pin => IndexRule::Pin {
+            index: pinned_index.unwrap_or_default(),
+        },
+        RuleKind::Merge => IndexRule::Merge,
+        RuleKind::Block => IndexRule::Block,
+    }
+}
+
+// Where the files of a project are looked up for an index.
+enum Source {
+    Local,
+    Parent(Box<Store>),
+}
+
 struct Dependency {
     pub kind: DependencyKind,
     pub specifier: String,
+    // Parsed PEP 508 requirement, `Requires-External` entries are not parsed.
+    pub requirement: Option<Requirement>,
+}
+
+impl Dependency {
+    fn new(kind: DependencyKind, specifier: &str) -> Dependency {
+        let requirement = match kind {
+            DependencyKind::RequiresExternal => None,
+            _ => specifier.parse::<Requirement>().ok(),
+        };
+
+        Dependency {
+            kind,
+            specifier: specifier.to_owned(),
+            requirement,
+        }
+    }
 }
 
 impl package::CoreMetadata {
-    fn get_dependencies(self) -> Vec<Dependency> {
+    fn get_dependencies(&self) -> Vec<Dependency> {
         // TODO: Check if Requires and Provides are part of PEP specifications.
 
         let mut dependencies = Vec::new();
 
         for value in &self.requires_dists {
-            dependencies.push(Dependency {
-                kind: DependencyKind::RequiresDist,
-                specifier: value.clone(),
-            })
+            dependencies.push(Dependency::new(DependencyKind::RequiresDist, value))
         }
+
         for value in &self.provides_dists {
-            dependencies.push(Dependency {
-                kind: DependencyKind::ProvidesDist,
-                specifier: value.clone(),
-            })
+            dependencies.push(Dependency::new(DependencyKind::ProvidesDist, value))
         }
 
         for value in &self.obsoletes_dists {
-            dependencies.push(Dependency {
-                kind: DependencyKind::ObsoletesDist,
-                specifier: value.clone(),
-            })
+            dependencies.push(Dependency::new(DependencyKind::ObsoletesDist, value))
         }
 
         for value in &self.requires_externals {
-            dependencies.push(Dependency {
-                kind: DependencyKind::RequiresExternal,
-                specifier: value.clone(),
-            })
+            dependencies.push(Dependency::new(DependencyKind::RequiresExternal, value))
         }
 
         dependencies
     }
 }
 
-fn canonicalize_version(version: &str, strip_trailing_zero: bool) -> String {
-    let mut parts = Vec::new();
+// Every change of an index is journaled, the id of the entry is the serial
+// used by mirrors (`changelog_since_serial`, `last_serial`).
+async fn add_journal_entry<'e, E>(
+    executor: E,
+    index_id: i32,
+    name: &str,
+    version: Option<&str>,
+    action: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query!(
+        r#"
+        INSERT INTO journals (index_id, name, version, action)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        index_id,
+        name,
+        version,
+        action,
+    )
+    .execute(executor)
+    .await?;
 
-    let re = Regex::new(r"^(\d+):?((\d+)(\.(\d+))*)([.-].+)?$").unwrap();
-    if let Some(captures) = re.captures(version) {
-        if let Some(epoch) = captures.get(1) {
-            let epoch_value = epoch.as_str();
-            if !epoch_value.is_empty() {
-                parts.push(format!("{}!", epoch_value));
-            }
-        }
+    Ok(())
+}
 
-        if let Some(release) = captures.get(2) {
-            let release_value = release.as_str();
-            if strip_trailing_zero {
-                let re_trailing_zero = Regex::new(r"(\.0)+$").unwrap();
+// Name of the canonical version backfill in `data_migrations`.
+const CANONICAL_VERSIONS_BACKFILL: &str = "canonical-versions";
 
-                let normalized_release = re_trailing_zero.replace_all(release_value, "");
-                parts.push(normalized_release.to_string());
-            } else {
-                parts.push(release_value.to_string());
-            }
-        }
+// Size of the reads used to stream a byte range of a file.
+const RANGE_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+// Size of the reads used to send an uploaded file to the object store.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+fn io_error(e: std::io::Error) -> PackageError {
+    PackageError::Storage(object_store::Error::Generic {
+        store: "upload",
+        source: Box::new(e),
+    })
+}
 
-        if let Some(pre_release) = captures.get(6) {
-            let pre_release_value = pre_release.as_str();
-            parts.push(pre_release_value.to_string());
+fn not_found(e: object_store::Error) -> PackageError {
+    match e {
+        object_store::Error::NotFound { .. } => PackageError::NotFound,
+        e => e.into(),
+    }
+}
+
+// Signs the short-lived URLs clients are redirected to, so that files are
+// downloaded from the object storage instead of through the index.
+#[derive(Clone)]
+pub struct Presigner {
+    signer: Arc<dyn Signer>,
+    // The signer sees the whole bucket, not the prefixed store.
+    prefix: Option<Path>,
+    ttl: Duration,
+}
+
+impl Presigner {
+    pub fn new(signer: Arc<dyn Signer>, prefix: Option<&str>, ttl: Duration) -> Presigner {
+        Presigner {
+            signer,
+            prefix: prefix.map(Path::from),
+            ttl,
         }
-    } else {
-        // Invalid version format, return the original input
-        return version.to_string();
     }
 
-    parts.join("")
+    async fn url(&self, path: &Path) -> Result<String, object_store::Error> {
+        let path = match &self.prefix {
+            Some(prefix) => prefix.parts().chain(path.parts()).collect(),
+            None => path.clone(),
+        };
+        let method = "GET".parse().expect("Valid HTTP method");
+
+        Ok(self
+            .signer
+            .signed_url(method, &path, self.ttl)
+            .await?
+            .into())
+    }
 }
 
 #[derive(Clone)]
 pub struct Store {
     db: PgPool,
     store: Arc<dyn ObjectStore>,
+    presigner: Option<Presigner>,
+    upstream: Option<Upstream>,
+    index_id: i32,
+    index: String,
 }
 
 impl Store {
-    pub fn new(db: PgPool, store: Arc<dyn ObjectStore>) -> Store {
-        Store { db, store }
+    pub fn new(
+        db: PgPool,
+        store: Arc<dyn ObjectStore>,
+        presigner: Option<Presigner>,
+        upstream: Option<Upstream>,
+    ) -> Store {
+        Store {
+            db,
+            store,
+            presigner,
+            upstream,
+            index_id: ROOT_INDEX_ID,
+            index: String::from(ROOT_INDEX),
+        }
     }
 
-    async fn create_project(&self, project_name: &str) -> Result<(), PackageError> {
-        let query = sqlx::query!(
+    // Same storage, scoped to another index.
+    fn scoped(&self, index_id: i32, index: &str) -> Store {
+        Store {
+            index_id,
+            index: index.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    // Only the root index looks at the upstream index, the other indexes see
+    // its projects through inheritance.
+    fn upstream(&self) -> Option<&Upstream> {
+        match self.index_id {
+            ROOT_INDEX_ID => self.upstream.as_ref(),
+            _ => None,
+        }
+    }
+
+    async fn get_parents(&self) -> Result<Vec<Store>, PackageError> {
+        let parents = sqlx::query!(
             r#"
-            INSERT INTO projects (name, normalized_name)
-            VALUES ($1, normalize_pep426_name($1))
+            SELECT i.id, i.name
+            FROM index_parents ip
+            JOIN indexes i ON i.id = ip.parent_id
+            WHERE ip.index_id = $1
+            ORDER BY ip.priority ASC
             "#,
-            project_name,
+            self.index_id
         )
-        .execute(&self.db)
-        .await;
+        .fetch_all(&self.db)
+        .await?;
 
-        if query.is_err() {
-            return Err(PackageError);
-        }
+        Ok(parents
+            .into_iter()
+            .map(|parent| self.scoped(parent.id, &parent.name))
+            .collect())
+    }
 
-        Ok(())
+    async fn find_index_rule(&self, project: &str) -> Result<Option<IndexRule>, PackageError> {
+        let rule = sqlx::query!(
+            r#"
+            SELECT o.rule as "rule: RuleKind", i.name as "pinned_index?"
+            FROM index_overrides o
+            LEFT JOIN indexes i ON i.id = o.pinned_index_id
+            WHERE o.index_id = $1
+                AND o.normalized_name = normalize_pep426_name($2)
+            "#,
+            self.index_id,
+            project
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(rule.map(|r| index_rule(r.rule, r.pinned_index)))
     }
 
-    async fn project_exists(&self, project_name: &str) -> Result<bool, PackageError> {
-        let query = sqlx::query!(
+    async fn find_project_id(&self, project: &str) -> Result<Option<i32>, PackageError> {
+        let project = sqlx::query!(
             r#"
-            SELECT name, normalized_name
+            SELECT id
             FROM projects
             WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $2
             "#,
-            project_name,
+            project,
+            self.index_id
         )
         .fetch_optional(&self.db)
-        .await;
+        .await?;
 
-        if let Ok(q) = query {
-            match q {
-                Some(_) => Ok(true),
-                _ => Ok(false),
-            }
-        } else {
-            Err(PackageError)
-        }
+        Ok(project.map(|p| p.id))
     }
 
-    async fn save_file_distribution(
-        &self,
-        project_name: &str,
-        dist_name: &str,
-        dist_content: &Bytes,
-    ) -> Result<(), PackageError> {
-        let file_path = Path::from_iter(["simple-index", project_name, dist_name]);
-
-        let query = self.store.put(&file_path, dist_content.to_owned()).await;
+    async fn get_project_id(&self, project: &str) -> Result<i32, PackageError> {
+        self.find_project_id(project)
+            .await?
+            .ok_or(PackageError::NotFound)
+    }
 
-        if query.is_err() {
-            return Err(PackageError);
-        }
+    // The uploader of a project without owner nor maintainer becomes its
+    // owner, unless the project already holds uploaded files: projects
+    // created before ownership are transferred by an admin. Concurrent
+    // uploads of a new project only keep one owner.
+    async fn claim_ownership<'e, E>(
+        &self,
+        executor: E,
+        project_id: i32,
+        user: &User,
+    ) -> Result<(), PackageError>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO project_roles (project_id, user_id, role)
+            SELECT $1, $2, 'owner'
+            WHERE NOT EXISTS (SELECT 1 FROM project_roles WHERE project_id = $1)
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM releases r
+                    JOIN release_files rf ON rf.release_id = r.id
+                    WHERE r.project_id = $1 AND rf.upstream_url IS NULL
+                )
+            ON CONFLICT DO NOTHING
+            "#,
+            project_id,
+            user.id(),
+        )
+        .execute(executor)
+        .await?;
 
         Ok(())
     }
-}
 
-#[async_trait]
-impl SimpleStore for Store {
-    async fn upload_package(
+    // Projects without owner nor maintainer, only holding files cached from
+    // the upstream index, stay open to every user. The ones created before
+    // ownership are left to the admins.
+    async fn check_maintainer(
         &self,
-        distribution: package::Distribution,
+        project_id: i32,
+        project: &str,
+        user: &User,
     ) -> Result<(), PackageError> {
-        let core_metadata = distribution.core_metadata;
-        let hashes = distribution.hashes;
+        if user.is_admin() {
+            return Ok(());
+        }
+
+        let roles = sqlx::query!(
+            "SELECT user_id FROM project_roles WHERE project_id = $1",
+            project_id
+        )
+        .fetch_all(&self.db)
+        .await?;
 
-        let filename = distribution.file.filename;
+        if roles.iter().any(|r| r.user_id == user.id()) {
+            return Ok(());
+        }
 
-        let project_exists = self
-            .project_exists(&core_metadata.name)
-            .await
-            .expect("Unable to check wether project exists or not.");
+        if roles.is_empty() {
+            let uploaded = sqlx::query!(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM releases r
+                    JOIN release_files rf ON rf.release_id = r.id
+                    WHERE r.project_id = $1 AND rf.upstream_url IS NULL
+                ) as "uploaded!"
+                "#,
+                project_id
+            )
+            .fetch_one(&self.db)
+            .await?;
+
+            if !uploaded.uploaded {
+                return Ok(());
+            }
+        }
+
+        tracing::warn!("{} isn't a maintainer of {}", user.username, project);
+        Err(PackageError::PermissionDenied(project.to_owned()))
+    }
 
-        if !project_exists {
-            tracing::info!("Create project {}", &core_metadata.name);
-            self.create_project(&core_metadata.name)
-                .await
-                .expect("Unable to create project");
+    async fn check_owner(
+        &self,
+        project_id: i32,
+        project: &str,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        if user.is_admin() {
+            return Ok(());
         }
 
-        let project = sqlx::query!(
+        let role = sqlx::query!(
             r#"
-            SELECT id FROM projects WHERE name = $1
+            SELECT role as "role: ProjectRole"
+            FROM project_roles
+            WHERE project_id = $1 AND user_id = $2
             "#,
-            &core_metadata.name
+            project_id,
+            user.id(),
         )
-        .fetch_one(&self.db)
-        .await
-        .expect("Unable to get record.");
-
-        let save_file = self
-            .save_file_distribution(&core_metadata.name, &filename, &distribution.file.content)
-            .await;
+        .fetch_optional(&self.db)
+        .await?;
 
-        if save_file.is_err() {
-            return Err(PackageError {});
+        match role {
+            Some(r) if r.role == ProjectRole::Owner => Ok(()),
+            _ => Err(PackageError::NotOwner(project.to_owned())),
         }
+    }
 
-        let size = distribution.file.content.len() as i32;
+    async fn find_user_id(&self, username: &str) -> Result<Uuid, PackageError> {
+        let user = sqlx::query!("SELECT id FROM users WHERE username = $1", username)
+            .fetch_optional(&self.db)
+            .await?;
 
-        let file_path = Path::from_iter(["simple-index", &core_metadata.name, &filename]);
+        user.map(|u| u.id)
+            .ok_or_else(|| PackageError::InvalidMaintainer(format!("unknown user {}", username)))
+    }
 
-        let mut tx = self.db.begin().await.expect("Unable to start transaction");
-        let release = sqlx::query!(
-            r#"
-            INSERT INTO releases(
-                version, canonical_version, is_prerelease, author, author_email, maintainer, maintainer_email, home_page, license, summary, keywords, platform, download_url, requires_python, project_id)
-            VALUES
-                ($1, $2, pep440_is_prerelease($1), $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
-            ON CONFLICT(project_id, canonical_version) DO UPDATE
-            SET
-                is_prerelease=pep440_is_prerelease($1),
-                author=$3,
-                author_email=$4,
-                maintainer=$5,
-                maintainer_email=$6,
-                home_page=$7,
-                license=$8,
-                summary=$9,
-                keywords=$10,
-                platform=$11,
-                download_url=$12,
-                requires_python=$13
-            RETURNING id
-            "#,
-            &core_metadata.version,
-            canonicalize_version(&core_metadata.version, false),
-            &core_metadata.author.as_deref().unwrap_or(""),
-            &core_metadata.author_email.as_deref().unwrap_or(""),
-            &core_metadata.maintainer.as_deref().unwrap_or(""),
-            &core_metadata.maintainer_email.as_deref().unwrap_or(""),
-            &core_metadata.home_page.as_deref().unwrap_or(""),
-            &core_metadata.license.as_deref().unwrap_or(""),
-            &core_metadata.summary.as_deref().unwrap_or(""),
-            &core_metadata.keywords.join(","),
-            &core_metadata.platforms.join(","),
-            &core_metadata.download_url.as_deref().unwrap_or(""),
-            &core_metadata.requires_python.as_deref().unwrap_or(""),
-            &project.id)
-                .fetch_one(&mut *tx)
-                .await
-                .unwrap();
+    // Sources of the files of the project in priority order, and whether
+    // they are merged. Otherwise the first source with files wins, so the
+    // projects of an index shadow the ones of its parents, unless all their
+    // releases were promoted.
+    async fn sources(&self, project: &str) -> Result<(Vec<Source>, bool), PackageError> {
+        let parents = self.get_parents().await?;
 
-        let release_id = release.id;
+        match self.find_index_rule(project).await? {
+            Some(IndexRule::Block) => Ok((Vec::new(), false)),
+            Some(IndexRule::Pin { index }) if index == self.index => Ok((vec![Source::Local], false)),
+            Some(IndexRule::Pin { index }) => Ok((
+                parents
+                    .into_iter()
+                    .filter(|parent| parent.index == index)
+                    .map(|parent| Source::Parent(Box::new(parent)))
+                    .collect(),
+                false,
+            )),
+            Some(IndexRule::Merge) => Ok((
+                std::iter::once(Source::Local)
+                    .chain(parents.into_iter().map(|parent| Source::Parent(Box::new(parent))))
+                    .collect(),
+                true,
+            )),
+            None => Ok((
+                std::iter::once(Source::Local)
+                    .chain(
+                        parents
+                            .into_iter()
+                            .map(|parent| Source::Parent(Box::new(parent))),
+                    )
+                    .collect(),
+                false,
+            )),
+        }
+    }
 
-        let _ = sqlx::query!(r#"
-            INSERT INTO release_files(
-                python_version, requires_python, packagetype, filename, path, size, md5_digest, sha256_digest, blake2_256_digest, release_id
-            )
-            VALUES
-                ($1, $2, $3, $4, $5, $6, $7, lower($8), lower($9), $10)
-            ON CONFLICT(filename) DO UPDATE
-            SET
-                python_version=$1,
-                requires_python=$2,
-                packagetype=$3,
-                path=$5,
-                size=$6,
-                md5_digest=$7,
-                sha256_digest=lower($8),
-                blake2_256_digest=lower($9)
-            "#,
-            &distribution.python_version.as_deref().unwrap_or(""),
-            &core_metadata.requires_python.as_deref().unwrap_or(""),
-            PackageType::BdistWheel as _,
-            &filename,
-            &file_path.to_string(),
-            &size,
-            &hashes.md5_digest,
-            &hashes.sha256_digest,
-            &hashes.blake2_256_digest,
-            &release_id,
-            )
-            .execute(&mut *tx)
-            .await
-            .expect("Unable to add release file");
+    // Copy the files of an upstream project which aren't stored yet, returns
+    // the number of copied files. Internal projects are left untouched.
+    pub async fn mirror_project(
+        &self,
+        upstream: &Upstream,
+        project: &str,
+        files: &[UpstreamFile],
+    ) -> Result<usize, PackageError> {
+        if self.find_upstream_policy(project).await? == Some(UpstreamPolicy::Internal) {
+            tracing::warn!("Skip the mirror of internal project {}", project);
+            return Ok(0);
+        }
 
-        if let Some(desc) = &core_metadata.description {
-            let description_type = &core_metadata
-                .description_content_type
-                .to_owned()
-                .unwrap_or("text/plain".to_string());
+        let stored = sqlx::query!(
+            r#"
+            SELECT rf.filename
+            FROM projects p
+            JOIN releases r ON p.id = r.project_id
+            JOIN release_files rf ON r.id = rf.release_id
+            WHERE p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $2
+            "#,
+            project,
+            self.index_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+        let stored: HashSet<String> = stored.into_iter().map(|f| f.filename).collect();
 
-            let description = match description_type {
-                t if t.contains("text/markdown") => {
-                    let parser = Parser::new(desc);
-                    let mut html_output = String::new();
+        let mut copied = 0;
+        for file in files {
+            if stored.contains(&file.filename) || file.version().is_none() {
+                continue;
+            }
+            self.cache_file(upstream, project, file).await?;
+            copied += 1;
+        }
 
-                    html::push_html(&mut html_output, parser);
+        Ok(copied)
+    }
 
-                    html_output
-                }
-                _ => String::new(),
-            };
+    // Releases saved before canonical versions followed `Version::canonical`
+    // are brought in line, so re-uploads resolve to the same release. A
+    // release whose canonical version is taken by an older release of its
+    // project is reported and left as is, an admin merges them. Runs once,
+    // concurrent startups wait for the first one to record it.
+    pub async fn backfill_canonical_versions(&self) -> Result<(), PackageError> {
+        let mut tx = self.db.begin().await?;
 
-            let _ = sqlx::query!(
-                r#"
-                INSERT INTO release_descriptions (
-                    content_type, raw, html, release_id
-                )
-                VALUES
-                    ($1, $2, $3, $4)
-                ON CONFLICT(release_id) DO UPDATE
-                SET
-                    content_type=$1,
-                    raw=$2,
-                    html=$3
-                "#,
-                &description_type,
-                desc,
-                description,
-                &release_id
-            )
-            .execute(&mut *tx)
-            .await;
+        let pending = sqlx::query!(
+            "INSERT INTO data_migrations (name) VALUES ($1) ON CONFLICT DO NOTHING",
+            CANONICAL_VERSIONS_BACKFILL
+        )
+        .execute(&mut *tx)
+        .await?;
+        if pending.rows_affected() == 0 {
+            return Ok(());
         }
 
-        let deps = core_metadata.get_dependencies();
-        let deps_number = deps.len() as i32;
+        let releases = sqlx::query!(
+            r#"
+            SELECT r.id, r.version, r.canonical_version, r.is_prerelease, p.name
+            FROM releases r
+            JOIN projects p ON p.id = r.project_id
+            ORDER BY r.id
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
 
-        if deps_number > 0 {
-            let (deps_kind, deps_specifier): (Vec<_>, Vec<_>) = deps
-                .into_iter()
-                .map(|Dependency { kind, specifier }| (kind, specifier.to_owned()))
-                .unzip();
+        for release in releases {
+            let Ok(version) = release.version.parse::<Version>() else {
+                continue;
+            };
+            let canonical_version = version.canonical();
+            let is_prerelease = version.is_prerelease();
 
-            let deps_release = vec![release_id, deps_number];
+            if canonical_version == release.canonical_version
+                && is_prerelease == release.is_prerelease
+            {
+                continue;
+            }
 
-            let _ = sqlx::query!(
+            let updated = sqlx::query!(
                 r#"
-            INSERT INTO release_dependencies (
-                kind, specifier, release_id)
-            SELECT * FROM UNNEST($1::"dependency_kind"[], $2::text[], $3::int[])
-            "#,
-                &deps_kind as _,
-                &deps_specifier,
-                &deps_release
+                UPDATE releases r
+                SET canonical_version = $2, is_prerelease = $3
+                WHERE id = $1
+                    AND NOT EXISTS (
+                        SELECT 1
+                        FROM releases
+                        WHERE project_id = r.project_id
+                            AND canonical_version = $2
+                            AND id <> $1
+                    )
+                "#,
+                release.id,
+                canonical_version,
+                is_prerelease,
             )
             .execute(&mut *tx)
-            .await;
-        }
-
-        let tx = tx.commit().await;
-
-        if tx.is_err() {
-            tracing::info!("Transaction failed, about to delete the file.");
+            .await?;
 
-            // TODO: Should we handle this error better ???
-            self.store
-                .delete(&file_path)
-                .await
-                .expect("Unable to delete file on aborted transactions.");
-            return Err(PackageError {});
+            if updated.rows_affected() == 0 {
+                tracing::warn!(
+                    "{} {} is the same version as another release of the project ({}), merge them",
+                    release.name,
+                    release.version,
+                    canonical_version
+                );
+            } else {
+                tracing::info!(
+                    "Set the canonical version of {} {} to {}",
+                    release.name,
+                    release.version,
+                    canonical_version
+                );
+            }
         }
 
+        tx.commit().await?;
+
         Ok(())
     }
 
-    async fn get_projects(&self) -> Result<Vec<ProjectName>, PackageError> {
-        let projects = sqlx::query_as!(
-            ProjectName,
+    // Serial of the upstream index the project was last mirrored at.
+    pub async fn get_mirror_serial(&self, project: &str) -> Result<Option<i64>, PackageError> {
+        let checkpoint = sqlx::query!(
             r#"
-            SELECT name FROM projects
-            ORDER BY name ASC
-            "#
+            SELECT last_serial
+            FROM mirror_checkpoints
+            WHERE normalized_name = normalize_pep426_name($1)
+            "#,
+            project
         )
-        .fetch_all(&self.db)
-        .await;
+        .fetch_optional(&self.db)
+        .await?;
 
-        match projects {
-            Ok(p) => Ok(p),
-            Err(_e) => Err(PackageError),
-        }
+        Ok(checkpoint.and_then(|c| c.last_serial))
     }
 
-    async fn get_dists(&self, project: &str) -> Result<Vec<PkgDist>, PackageError> {
-        let pkg_dists = sqlx::query_as!(
-            PkgDist,
+    pub async fn set_mirror_serial(
+        &self,
+        project: &str,
+        serial: Option<i64>,
+    ) -> Result<(), PackageError> {
+        sqlx::query!(
             r#"
-            WITH SelectedProject AS (
-                SELECT id
-                FROM projects
-                WHERE normalized_name = normalize_pep426_name($1)
-            )
-            SELECT rf.filename as filename, rf.path as path
-            FROM SelectedProject sr
-            JOIN releases r ON sr.id = r.project_id
-            JOIN release_files rf ON r.id = rf.release_id;
+            INSERT INTO mirror_checkpoints (normalized_name, last_serial)
+            VALUES (normalize_pep426_name($1), $2)
+            ON CONFLICT(normalized_name) DO UPDATE
+            SET last_serial = $2, synced_at = now()
             "#,
-            project
+            project,
+            serial
         )
-        .fetch_all(&self.db)
-        .await;
+        .execute(&self.db)
+        .await?;
 
-        if let Ok(pkg_dists) = pkg_dists {
-            Ok(pkg_dists)
-        } else {
-            Err(PackageError {})
+        Ok(())
+    }
+
+    // Policy of the project, `None` when the project isn't known to Nest.
+    async fn find_upstream_policy(
+        &self,
+        project: &str,
+    ) -> Result<Option<UpstreamPolicy>, PackageError> {
+        match self.get_upstream_policy(project).await {
+            Ok(policy) => Ok(Some(policy)),
+            Err(PackageError::NotFound) => Ok(None),
+            Err(e) => Err(e),
         }
     }
 
-    async fn get_dist_file(
+    // The upstream index is never looked at for internal projects.
+    fn upstream_for(&self, policy: Option<UpstreamPolicy>) -> Option<&Upstream> {
+        match policy {
+            Some(UpstreamPolicy::Internal) => None,
+            _ => self.upstream(),
+        }
+    }
+
+    // New projects can't take the name of an upstream project, unless an
+    // administrator claimed it.
+    async fn check_upload_policy(&self, project: &str) -> Result<(), PackageError> {
+        match self.find_upstream_policy(project).await? {
+            Some(UpstreamPolicy::Internal | UpstreamPolicy::Merged) => Ok(()),
+            Some(UpstreamPolicy::Upstream) => {
+                Err(PackageError::UpstreamConflict(project.to_owned()))
+            }
+            // Uploads fail while the upstream index can't be reached, the
+            // project could be one of its own.
+            None => match self.upstream() {
+                Some(upstream) if !upstream.get_files(project).await?.is_empty() => {
+                    tracing::warn!("Reject the upload of upstream project {}", project);
+                    Err(PackageError::UpstreamConflict(project.to_owned()))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    // Fetch a file of the upstream index and store it like an uploaded file,
+    // it is served from the object store afterwards.
+    async fn cache_upstream_file(
         &self,
+        upstream: &Upstream,
         project: &str,
         dist: &str,
-    ) -> Result<package::File, PackageError> {
-        let file_path = Path::from_iter(["simple-index", project, dist]);
-        let file = self.store.get(&file_path).await;
+    ) -> Result<(), PackageError> {
+        let file = upstream
+            .get_files(project)
+            .await?
+            .into_iter()
+            .find(|f| f.filename == dist)
+            .ok_or(PackageError::NotFound)?;
+
+        self.cache_file(upstream, project, &file).await?;
+        Ok(())
+    }
+
+    async fn cache_file(
+        &self,
+        upstream: &Upstream,
+        project: &str,
+        file: &UpstreamFile,
+    ) -> Result<String, PackageError> {
+        tracing::info!("Cache {} from {}", file.filename, file.url);
+        let content = upstream.download(file).await?;
+
+        let file_path = Path::from_iter(["simple-index", project, &file.filename]);
+        let staged_name = format!("{}.{}.upload", file.filename, Uuid::new_v4());
+        let staged_file = Path::from_iter(["simple-index", project, &staged_name]);
+        self.save_file_distribution(&staged_file, &content.content)
+            .await?;
+
+        let staged = [(staged_file, file_path)];
+
+        // The file may have been cached by a concurrent download.
+        match self
+            .save_cached_file(project, file, &content, &staged[0].0)
+            .await
+        {
+            Ok(true) => self.move_staged_files(&file.filename, &staged).await,
+            Ok(false) => self.delete_staged_files(&staged).await,
+            Err(e) => {
+                self.delete_staged_files(&staged).await;
+                return Err(e);
+            }
+        }
+
+        Ok(content.hashes.sha256_digest)
+    }
+
+    // Cached files only carry what the simple API lists, their release has
+    // no core metadata. Returns false when the file was already cached.
+    async fn save_cached_file(
+        &self,
+        project: &str,
+        file: &UpstreamFile,
+        content: &UpstreamContent,
+        file_path: &Path,
+    ) -> Result<bool, PackageError> {
+        let version_str = file.version().ok_or(PackageError::NotFound)?;
+        let version: Option<Version> = version_str.parse().ok();
+        let canonical_version = version
+            .as_ref()
+            .map(Version::canonical)
+            .unwrap_or_else(|| version_str.to_owned());
+        let is_prerelease = version.as_ref().is_some_and(Version::is_prerelease);
+        let size = i64::try_from(content.size).map_err(|_| PackageError::Processing)?;
+
+        let wheel = file.wheel();
+        let python_version = match &wheel {
+            Some(wheel) => wheel.python_tags.join("."),
+            None => String::from("source"),
+        };
+
+        let mut tx = self.db.begin().await?;
+        let (project_id, _) = self
+            .get_or_create_project(&mut tx, project, UpstreamPolicy::Upstream)
+            .await?;
+        let release = sqlx::query!(
+            r#"
+            INSERT INTO releases(version, canonical_version, is_prerelease, project_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT(project_id, canonical_version) DO UPDATE
+            SET version=releases.version
+            RETURNING id
+            "#,
+            &version_str,
+            canonical_version,
+            is_prerelease,
+            &project_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let inserted = sqlx::query!(r#"
+            INSERT INTO release_files(
+                python_version, requires_python, packagetype, filename, path, size, md5_digest, sha256_digest, blake2_256_digest, release_id, build_tag, python_tags, abi_tags, platform_tags, yanked, yanked_reason, upstream_url
+            )
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, lower($8), lower($9), $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT(release_id, filename) DO NOTHING
+            "#,
+            python_version,
+            file.requires_python.as_deref().unwrap_or(""),
+            file.package_type().map(PackageType::from) as _,
+            &file.filename,
+            &file_path.to_string(),
+            size,
+            &content.hashes.md5_digest,
+            &content.hashes.sha256_digest,
+            &content.hashes.blake2_256_digest,
+            release.id,
+            wheel.as_ref().and_then(|w| w.build_tag.as_deref()),
+            &wheel.as_ref().map(|w| w.python_tags.clone()).unwrap_or_default(),
+            &wheel.as_ref().map(|w| w.abi_tags.clone()).unwrap_or_default(),
+            &wheel.as_ref().map(|w| w.platform_tags.clone()).unwrap_or_default(),
+            file.yanked,
+            file.yanked_reason.as_deref(),
+            file.url.as_str(),
+            )
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(inserted.rows_affected() > 0)
+    }
+
+    // Projects are created on their first upload or cached file, concurrent
+    // uploads of a new project may both try to create it. The policy only
+    // applies to new projects. The creation is journaled in the transaction
+    // of the caller, returns whether the project was created.
+    async fn get_or_create_project(
+        &self,
+        conn: &mut PgConnection,
+        project_name: &str,
+        policy: UpstreamPolicy,
+    ) -> Result<(i32, bool), PackageError> {
+        let created = sqlx::query!(
+            r#"
+            INSERT INTO projects (name, normalized_name, upstream_policy, index_id)
+            VALUES ($1, normalize_pep426_name($1), $2, $3)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            "#,
+            project_name,
+            policy as _,
+            self.index_id,
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(project) = created {
+            tracing::info!("Create project {} in index {}", project_name, self.index);
+            add_journal_entry(&mut *conn, self.index_id, project_name, None, "create").await?;
+            return Ok((project.id, true));
+        }
+
+        let project = sqlx::query!(
+            r#"
+            SELECT id
+            FROM projects
+            WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $2
+            "#,
+            project_name,
+            self.index_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(PackageError::NotFound)?;
+
+        Ok((project.id, false))
+    }
+
+    // The spooled file is sent to the object store as a multipart upload,
+    // read chunk by chunk, it is never fully loaded in memory.
+    async fn save_file_distribution(
+        &self,
+        file_path: &Path,
+        content: &NamedTempFile,
+    ) -> Result<(), PackageError> {
+        let file = tokio::fs::File::from_std(content.reopen().map_err(io_error)?);
+        let mut reader = tokio::io::BufReader::with_capacity(UPLOAD_CHUNK_SIZE, file);
+        let (multipart_id, mut writer) = self.store.put_multipart(file_path).await?;
 
-        match file {
-            Ok(file) => {
-                let content = file.bytes().await.expect("Unable to decode wheel content");
-                let filename = dist.to_owned();
+        let copied = async {
+            tokio::io::copy_buf(&mut reader, &mut writer).await?;
+            writer.shutdown().await
+        }
+        .await;
 
-                Ok(package::File { filename, content })
+        if let Err(e) = copied {
+            if let Err(e) = self.store.abort_multipart(file_path, &multipart_id).await {
+                tracing::error!("Unable to abort the upload of {}: {}", file_path, e);
             }
-            _ => {
-                todo!()
+            return Err(io_error(e));
+        }
+
+        Ok(())
+    }
+
+    // Filenames can't be reused with a different content, unless the project
+    // allows overwrites. Returns true when the same file was already uploaded
+    // to the project, filenames are unique across indexes.
+    async fn check_existing_file(
+        &self,
+        project_id: Option<i32>,
+        filename: &str,
+        sha256_digest: &str,
+    ) -> Result<bool, PackageError> {
+        let existing = sqlx::query!(
+            r#"
+            SELECT
+                rf.sha256_digest::TEXT as "sha256_digest!",
+                p.allow_file_overwrite,
+                COALESCE(p.id = $2, FALSE) as "same_project!"
+            FROM release_files rf
+            JOIN releases r ON r.id = rf.release_id
+            JOIN projects p ON p.id = r.project_id
+            WHERE rf.filename = $1
+            "#,
+            filename,
+            project_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        // Promoted copies of the file belong to other projects, they keep
+        // it from being overwritten.
+        if existing.is_empty() {
+            Ok(false)
+        } else if existing
+            .iter()
+            .any(|file| file.same_project && file.sha256_digest.eq_ignore_ascii_case(sha256_digest))
+        {
+            Ok(true)
+        } else if existing
+            .iter()
+            .all(|file| file.same_project && file.allow_file_overwrite)
+        {
+            Ok(false)
+        } else {
+            Err(PackageError::FileExists(filename.to_owned()))
+        }
+    }
+
+    // Files of an aborted upload must not be served.
+    async fn delete_staged_files(&self, staged: &[(Path, Path)]) {
+        for (path, _) in staged {
+            if let Err(e) = self.store.delete(path).await {
+                tracing::error!("Unable to delete {} of an aborted upload: {}", path, e);
             }
         }
     }
 
-    async fn get_dist_metadata(
+    // Committed files are served from their staged path until they are moved,
+    // a failed move leaves the file available under its staged name. Object
+    // stores rename by copy and delete, the row follows the copy.
+    async fn move_staged_files(&self, filename: &str, staged: &[(Path, Path)]) {
+        for (staged_path, path) in staged {
+            if let Err(e) = self.move_staged_file(filename, staged_path, path).await {
+                tracing::error!("Unable to move {} to {}: {}", staged_path, path, e);
+            }
+        }
+    }
+
+    async fn move_staged_file(
+        &self,
+        filename: &str,
+        staged_path: &Path,
+        path: &Path,
+    ) -> Result<(), PackageError> {
+        self.store.copy(staged_path, path).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE release_files
+            SET
+                path = CASE WHEN path = $2 THEN $3 ELSE path END,
+                metadata_path = CASE WHEN metadata_path = $2 THEN $3 ELSE metadata_path END
+            WHERE filename = $1
+            "#,
+            filename,
+            staged_path.to_string(),
+            path.to_string(),
+        )
+        .execute(&self.db)
+        .await?;
+
+        if let Err(e) = self.store.delete(staged_path).await {
+            tracing::warn!("Unable to delete the staged file {}: {}", staged_path, e);
+        }
+
+        Ok(())
+    }
+
+    // Save the release and the file in the transaction of the upload.
+    async fn save_release_file(
         &self,
-        _project: &str,
-        _dist: &str,
-    ) -> Result<package::CoreMetadata, PackageError> {
-        todo!()
+        conn: &mut PgConnection,
+        project_id: i32,
+        distribution: &package::Distribution,
+        file_path: &Path,
+        metadata_path: Option<&Path>,
+    ) -> Result<(), PackageError> {
+        let core_metadata = &distribution.core_metadata;
+        let hashes = &distribution.hashes;
+
+        let filename = &distribution.file.filename;
+        let wheel = distribution.wheel.as_ref();
+
+        // PEP 658 - The core metadata is stored next to the distribution.
+        let metadata_sha256_digest = distribution
+            .metadata_file
+            .as_ref()
+            .map(|metadata| hashes::sha256_digest(metadata));
+        let metadata_blake2_256_digest = distribution
+            .metadata_file
+            .as_ref()
+            .map(|metadata| hashes::blake2_256_digest(metadata));
+
+        let size = i64::try_from(distribution.file.size).map_err(|_| PackageError::Processing)?;
+
+        // Versions were validated with the distribution, a release is
+        // identified by its canonical version so `1.0` and `1.0.0` are the same.
+        let version: Option<Version> = core_metadata.version.parse().ok();
+        let canonical_version = version
+            .as_ref()
+            .map(Version::canonical)
+            .unwrap_or_else(|| core_metadata.version.to_owned());
+        let is_prerelease = version.as_ref().is_some_and(Version::is_prerelease);
+
+        let release = sqlx::query!(
+            r#"
+            INSERT INTO releases(
+                version, canonical_version, is_prerelease, author, author_email, maintainer, maintainer_email, home_page, license, summary, keywords, platform, download_url, requires_python, project_id, classifiers, project_urls, provides_extras)
+            VALUES
+                ($1, $2, $15, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $16, $17, $18)
+            ON CONFLICT(project_id, canonical_version) DO UPDATE
+            SET
+                is_prerelease=$15,
+                author=$3,
+                author_email=$4,
+                maintainer=$5,
+                maintainer_email=$6,
+                home_page=$7,
+                license=$8,
+                summary=$9,
+                keywords=$10,
+                platform=$11,
+                download_url=$12,
+                requires_python=$13,
+                classifiers=$16,
+                project_urls=$17,
+                provides_extras=$18
+            RETURNING id, (xmax = 0) as "created!"
+            "#,
+            &core_metadata.version,
+            canonical_version,
+            &core_metadata.author.as_deref().unwrap_or(""),
+            &core_metadata.author_email.as_deref().unwrap_or(""),
+            &core_metadata.maintainer.as_deref().unwrap_or(""),
+            &core_metadata.maintainer_email.as_deref().unwrap_or(""),
+            &core_metadata.home_page.as_deref().unwrap_or(""),
+            &core_metadata.license.as_deref().unwrap_or(""),
+            &core_metadata.summary.as_deref().unwrap_or(""),
+            &core_metadata.keywords.join(","),
+            &core_metadata.platforms.join(","),
+            &core_metadata.download_url.as_deref().unwrap_or(""),
+            &core_metadata.requires_python.as_deref().unwrap_or(""),
+            &project_id,
+            is_prerelease,
+            &core_metadata.classifiers,
+            &core_metadata.project_urls,
+            &core_metadata.provides_extras)
+                .fetch_one(&mut *conn)
+                .await?;
+
+        let release_id = release.id;
+
+        if release.created {
+            add_journal_entry(
+                &mut *conn,
+                self.index_id,
+                &core_metadata.name,
+                Some(&core_metadata.version),
+                "new release",
+            )
+            .await?;
+        }
+
+        let inserted = sqlx::query!(r#"
+            INSERT INTO release_files(
+                python_version, requires_python, packagetype, filename, path, size, md5_digest, sha256_digest, blake2_256_digest, metadata_file_sha256_digest, metadata_file_blake2_256_digest, release_id, build_tag, python_tags, abi_tags, platform_tags, metadata_path
+            )
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, lower($8), lower($9), lower($10), lower($11), $12, $13, $14, $15, $16, $18)
+            ON CONFLICT(release_id, filename) DO UPDATE
+            SET
+                python_version=$1,
+                requires_python=$2,
+                packagetype=$3,
+                path=$5,
+                size=$6,
+                md5_digest=$7,
+                sha256_digest=lower($8),
+                blake2_256_digest=lower($9),
+                metadata_file_sha256_digest=lower($10),
+                metadata_file_blake2_256_digest=lower($11),
+                build_tag=$13,
+                python_tags=$14,
+                abi_tags=$15,
+                platform_tags=$16,
+                release_id=$12,
+                metadata_path=$18
+            WHERE EXISTS (
+                SELECT 1
+                FROM releases r
+                JOIN projects p ON p.id = r.project_id
+                WHERE r.id = release_files.release_id
+                    AND p.id = $17
+                    AND p.allow_file_overwrite
+            )
+            "#,
+            &distribution.python_version.as_deref().unwrap_or(""),
+            &core_metadata.requires_python.as_deref().unwrap_or(""),
+            PackageType::from(distribution.package_type) as _,
+            filename,
+            &file_path.to_string(),
+            &size,
+            &hashes.md5_digest,
+            &hashes.sha256_digest,
+            &hashes.blake2_256_digest,
+            metadata_sha256_digest.as_deref(),
+            metadata_blake2_256_digest.as_deref(),
+            &release_id,
+            wheel.and_then(|w| w.build_tag.as_deref()),
+            &wheel.map(|w| w.python_tags.clone()).unwrap_or_default(),
+            &wheel.map(|w| w.abi_tags.clone()).unwrap_or_default(),
+            &wheel.map(|w| w.platform_tags.clone()).unwrap_or_default(),
+            &project_id,
+            metadata_path.map(|path| path.to_string()),
+            )
+            .execute(&mut *conn)
+            .await?;
+
+        // The file was uploaded concurrently, or belongs to a project which
+        // doesn't allow overwrites.
+        if inserted.rows_affected() == 0 {
+            return Err(PackageError::FileExists(filename.to_owned()));
+        }
+
+        let python_version = distribution
+            .python_version
+            .as_deref()
+            .filter(|v| !v.is_empty())
+            .unwrap_or("source");
+        add_journal_entry(
+            &mut *conn,
+            self.index_id,
+            &core_metadata.name,
+            Some(&core_metadata.version),
+            &format!("add {} file {}", python_version, filename),
+        )
+        .await?;
+
+        if let Some(desc) = &core_metadata.description {
+            let description_type = &core_metadata
+                .description_content_type
+                .to_owned()
+                .unwrap_or("text/plain".to_string());
+
+            let description = match description_type {
+                t if t.contains("text/markdown") => {
+                    let parser = Parser::new(desc);
+                    let mut html_output = String::new();
+
+                    html::push_html(&mut html_output, parser);
+
+                    html_output
+                }
+                _ => String::new(),
+            };
+
+            sqlx::query!(
+                r#"
+                INSERT INTO release_descriptions (
+                    content_type, raw, html, release_id
+                )
+                VALUES
+                    ($1, $2, $3, $4)
+                ON CONFLICT(release_id) DO UPDATE
+                SET
+                    content_type=$1,
+                    raw=$2,
+                    html=$3
+                "#,
+                &description_type,
+                desc,
+                description,
+                &release_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        // Dependencies belong to the release, every file of the release
+        // carries the same ones.
+        sqlx::query!(
+            "DELETE FROM release_dependencies WHERE release_id = $1",
+            release_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let deps = core_metadata.get_dependencies();
+        let deps_number = deps.len();
+
+        if deps_number > 0 {
+            let mut deps_kind = Vec::with_capacity(deps_number);
+            let mut deps_specifier = Vec::with_capacity(deps_number);
+            let mut deps_name = Vec::with_capacity(deps_number);
+            let mut deps_extras = Vec::with_capacity(deps_number);
+            let mut deps_version_specifier = Vec::with_capacity(deps_number);
+            let mut deps_url = Vec::with_capacity(deps_number);
+            let mut deps_marker = Vec::with_capacity(deps_number);
+
+            for Dependency { kind, specifier, requirement } in deps {
+                deps_kind.push(kind);
+                deps_specifier.push(specifier);
+
+                let requirement = requirement.as_ref();
+                deps_name.push(requirement.map(|r| r.name.to_owned()));
+                // Extras are joined here and split back in the query, UNNEST
+                // can't expand arrays of different lengths.
+                deps_extras.push(requirement.map(|r| r.extras.join(",")).unwrap_or_default());
+                deps_version_specifier.push(requirement.and_then(|r| r.specifier.to_owned()));
+                deps_url.push(requirement.and_then(|r| r.url.to_owned()));
+                deps_marker.push(requirement.and_then(|r| r.marker.to_owned()));
+            }
+
+            let deps_release = vec![release_id; deps_number];
+
+            sqlx::query!(
+                r#"
+            INSERT INTO release_dependencies (
+                kind, specifier, name, extras, version_specifier, url, marker, release_id)
+            SELECT kind, specifier, name, string_to_array(extras, ','), version_specifier, url, marker, release_id
+            FROM UNNEST($1::"dependency_kind"[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[], $8::int[])
+                AS d(kind, specifier, name, extras, version_specifier, url, marker, release_id)
+            "#,
+                &deps_kind as _,
+                &deps_specifier,
+                &deps_name as _,
+                &deps_extras,
+                &deps_version_specifier as _,
+                &deps_url as _,
+                &deps_marker as _,
+                &deps_release
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_local_projects(&self) -> Result<Vec<ProjectName>, PackageError> {
+        let projects = sqlx::query_as!(
+            ProjectName,
+            r#"
+            SELECT name FROM projects
+            WHERE index_id = $1
+            ORDER BY name ASC
+            "#,
+            self.index_id
+        )
+        .fetch_all(&self.db)
+        .await;
+
+        Ok(projects?)
+    }
+
+    async fn get_local_dists(&self, project: &str) -> Result<Vec<PkgDist>, PackageError> {
+        let pkg_dists = sqlx::query_as!(
+            PkgDist,
+            r#"
+            WITH SelectedProject AS (
+                SELECT id, upstream_policy
+                FROM projects
+                WHERE normalized_name = normalize_pep426_name($1)
+                    AND index_id = $2
+            )
+            SELECT
+                rf.filename as filename,
+                rf.path as path,
+                r.version as version,
+                rf.size as size,
+                rf.sha256_digest::TEXT as "sha256_digest!",
+                NULLIF(rf.requires_python, '') as requires_python,
+                rf.metadata_file_sha256_digest::TEXT as metadata_sha256_digest,
+                (rf.yanked OR r.yanked) as "yanked!",
+                COALESCE(rf.yanked_reason, r.yanked_reason) as yanked_reason
+            FROM SelectedProject sr
+            JOIN releases r ON sr.id = r.project_id
+            JOIN release_files rf ON r.id = rf.release_id
+            WHERE CASE sr.upstream_policy
+                WHEN 'internal' THEN rf.upstream_url IS NULL
+                WHEN 'upstream' THEN rf.upstream_url IS NOT NULL
+                ELSE TRUE
+            END
+            ORDER BY rf.filename ASC;
+            "#,
+            project,
+            self.index_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let policy = self.find_upstream_policy(project).await?;
+        let Some(upstream) = self.upstream_for(policy) else {
+            return Ok(pkg_dists);
+        };
+
+        // The page stays available with the known files when the upstream
+        // index can't be reached.
+        let files = match upstream.get_files(project).await {
+            Ok(files) => files,
+            Err(e) => {
+                tracing::warn!("Unable to list {} on the upstream index: {}", project, e);
+                return Ok(pkg_dists);
+            }
+        };
+
+        // Known files take precedence over the upstream files of the same
+        // name. Files are cached with their release, the ones without a
+        // version in their filename can't be served.
+        let mut pkg_dists = pkg_dists;
+        let known: HashSet<String> = pkg_dists.iter().map(|d| d.filename.to_owned()).collect();
+
+        for file in files.into_iter().filter(|f| !known.contains(&f.filename)) {
+            let Some(version) = file.version() else {
+                continue;
+            };
+
+            pkg_dists.push(PkgDist {
+                path: Path::from_iter(["simple-index", project, &file.filename]).to_string(),
+                version,
+                size: file.size.and_then(|size| i64::try_from(size).ok()),
+                sha256_digest: file.sha256_digest,
+                requires_python: file.requires_python,
+                metadata_sha256_digest: None,
+                yanked: file.yanked,
+                yanked_reason: file.yanked_reason,
+                filename: file.filename,
+            });
+        }
+
+        pkg_dists.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        Ok(pkg_dists)
+    }
+
+    // Files are served from the path they were stored at, it follows the
+    // project name of their upload, not the one of the request.
+    async fn find_local_dist_file(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<Option<(String, String)>, PackageError> {
+        let file = sqlx::query!(
+            r#"
+            SELECT rf.path, rf.sha256_digest::TEXT as "sha256_digest!"
+            FROM projects p
+            JOIN releases r ON p.id = r.project_id
+            JOIN release_files rf ON r.id = rf.release_id
+            WHERE p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $3
+                AND rf.filename = $2
+                AND CASE p.upstream_policy
+                    WHEN 'internal' THEN rf.upstream_url IS NULL
+                    WHEN 'upstream' THEN rf.upstream_url IS NOT NULL
+                    ELSE TRUE
+                END
+            "#,
+            project,
+            dist,
+            self.index_id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(file.map(|file| (file.path, file.sha256_digest)))
+    }
+
+    async fn get_local_dist_file(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<package::DistFile, PackageError> {
+        let file = match self.find_local_dist_file(project, dist).await? {
+            Some(file) => file,
+            None => {
+                let policy = self.find_upstream_policy(project).await?;
+                let upstream = self.upstream_for(policy).ok_or(PackageError::NotFound)?;
+                self.cache_upstream_file(upstream, project, dist).await?;
+                self.find_local_dist_file(project, dist)
+                    .await?
+                    .ok_or(PackageError::NotFound)?
+            }
+        };
+        let (path, sha256_digest) = file;
+
+        let meta = self
+            .store
+            .head(&Path::from(path.as_str()))
+            .await
+            .map_err(not_found)?;
+
+        Ok(package::DistFile {
+            path,
+            filename: dist.to_owned(),
+            size: meta.size,
+            sha256_digest,
+            last_modified: meta.last_modified.into(),
+        })
+    }
+
+    async fn get_local_dist_metadata(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<package::File, PackageError> {
+        let file = sqlx::query!(
+            r#"
+            SELECT rf.metadata_path as "metadata_path!"
+            FROM projects p
+            JOIN releases r ON p.id = r.project_id
+            JOIN release_files rf ON r.id = rf.release_id
+            WHERE p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $3
+                AND rf.filename = $2
+                AND rf.metadata_path IS NOT NULL
+                AND CASE p.upstream_policy
+                    WHEN 'internal' THEN rf.upstream_url IS NULL
+                    WHEN 'upstream' THEN rf.upstream_url IS NOT NULL
+                    ELSE TRUE
+                END
+            "#,
+            project,
+            dist,
+            self.index_id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(PackageError::NotFound)?;
+
+        let file_path = Path::from(file.metadata_path.as_str());
+        let file = self.store.get(&file_path).await.map_err(not_found)?;
+        let content = file.bytes().await?;
+
+        Ok(package::File {
+            filename: format!("{}.metadata", dist),
+            content,
+        })
+    }
+}
+
+#[async_trait]
+impl SimpleStore for Store {
+    async fn upload_package(
+        &self,
+        distribution: package::Distribution,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let core_metadata = &distribution.core_metadata;
+        let filename = &distribution.file.filename;
+
+        self.check_upload_policy(&core_metadata.name).await?;
+
+        // New projects are created with their first release, by then they
+        // may have been created by a concurrent upload.
+        let existing_project_id = self.find_project_id(&core_metadata.name).await?;
+        if let Some(project_id) = existing_project_id {
+            self.check_maintainer(project_id, &core_metadata.name, user)
+                .await?;
+        }
+
+        // An identical re-upload is a no-op, so `twine upload --skip-existing`
+        // and retried uploads succeed.
+        if self
+            .check_existing_file(
+                existing_project_id,
+                filename,
+                &distribution.hashes.sha256_digest,
+            )
+            .await?
+        {
+            tracing::info!("File {} already exists, skip it.", filename);
+            return Ok(());
+        }
+
+        let file_path = Path::from_iter(["simple-index", &core_metadata.name, filename]);
+        let metadata_name = format!("{}.metadata", filename);
+        let metadata_path = Path::from_iter(["simple-index", &core_metadata.name, &metadata_name]);
+
+        // Files are staged under a temporary name and only moved to their final
+        // path once the release is committed. A failed upload never leaves an
+        // orphan file in the index, nor replaces an existing one. The release
+        // is committed with the staged paths, they are served until the move.
+        let upload_id = Uuid::new_v4();
+        let staged_name = |name: &str| format!("{}.{}.upload", name, upload_id);
+        let staged_file =
+            Path::from_iter(["simple-index", &core_metadata.name, &staged_name(filename)]);
+        self.save_file_distribution(&staged_file, &distribution.file.content)
+            .await?;
+
+        let mut staged = vec![(staged_file.clone(), file_path)];
+        let mut staged_metadata_path = None;
+
+        // PEP 658 - The core metadata is stored next to the distribution.
+        if let Some(metadata) = &distribution.metadata_file {
+            let staged_metadata = Path::from_iter([
+                "simple-index",
+                &core_metadata.name,
+                &staged_name(&metadata_name),
+            ]);
+            if let Err(e) = self.store.put(&staged_metadata, metadata.to_owned()).await {
+                self.delete_staged_files(&staged).await;
+                return Err(e.into());
+            }
+            staged_metadata_path = Some(staged_metadata.clone());
+            staged.push((staged_metadata, metadata_path));
+        }
+
+        let saved = async {
+            let mut tx = self.db.begin().await?;
+            let (project_id, created) = self
+                .get_or_create_project(&mut tx, &core_metadata.name, UpstreamPolicy::Internal)
+                .await?;
+            if !created && existing_project_id.is_none() {
+                self.check_maintainer(project_id, &core_metadata.name, user)
+                    .await?;
+            }
+            // Claimed with the release, a rejected upload grants no role.
+            self.claim_ownership(&mut *tx, project_id, user).await?;
+
+            self.save_release_file(
+                &mut tx,
+                project_id,
+                &distribution,
+                &staged_file,
+                staged_metadata_path.as_ref(),
+            )
+            .await?;
+
+            tx.commit().await?;
+            Ok::<_, PackageError>(())
+        }
+        .await;
+
+        if let Err(e) = saved {
+            tracing::info!("Unable to save the release, about to delete the staged files.");
+            self.delete_staged_files(&staged).await;
+            return Err(e);
+        }
+
+        self.move_staged_files(filename, &staged).await;
+
+        Ok(())
+    }
+
+    async fn get_projects(&self) -> Result<Vec<ProjectName>, PackageError> {
+        let mut projects = self.get_local_projects().await?;
+        for parent in self.get_parents().await? {
+            projects.extend(parent.get_projects().await?);
+        }
+
+        let blocked = sqlx::query!(
+            r#"
+            SELECT normalized_name
+            FROM index_overrides
+            WHERE index_id = $1 AND rule = 'block'
+            "#,
+            self.index_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+        let mut hidden: HashSet<String> = blocked.into_iter().map(|b| b.normalized_name).collect();
+
+        // Projects of the index come first, they shadow the ones of the parents.
+        projects.retain(|p| hidden.insert(package::normalize_project_name(&p.name)));
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(projects)
+    }
+
+    async fn get_releases(&self, project: &str) -> Result<Vec<Release>, PackageError> {
+        let releases = sqlx::query_as!(
+            Release,
+            r#"
+            SELECT r.version, r.yanked, r.yanked_reason
+            FROM projects p
+            JOIN releases r ON p.id = r.project_id
+            WHERE p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $2
+            "#,
+            project,
+            self.index_id
+        )
+        .fetch_all(&self.db)
+        .await;
+
+        let mut releases = releases?;
+
+        // Newest release first, following PEP 440 ordering.
+        releases.sort_by_cached_key(|r| std::cmp::Reverse(r.version.parse::<Version>().ok()));
+
+        Ok(releases)
+    }
+
+    async fn get_dists(&self, project: &str) -> Result<Vec<PkgDist>, PackageError> {
+        let (sources, merge) = self.sources(project).await?;
+
+        let mut pkg_dists: Vec<PkgDist> = Vec::new();
+        for source in sources {
+            let dists = match source {
+                Source::Local => self.get_local_dists(project).await?,
+                Source::Parent(parent) => parent.get_dists(project).await?,
+            };
+
+            // The files of the first sources take precedence.
+            for dist in dists {
+                if !pkg_dists.iter().any(|d| d.filename == dist.filename) {
+                    pkg_dists.push(dist);
+                }
+            }
+
+            if !merge && !pkg_dists.is_empty() {
+                break;
+            }
+        }
+
+        pkg_dists.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+        Ok(pkg_dists)
+    }
+
+    async fn get_dist_file(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<package::DistFile, PackageError> {
+        let (sources, _) = self.sources(project).await?;
+
+        for source in sources {
+            let file = match source {
+                Source::Local => self.get_local_dist_file(project, dist).await,
+                Source::Parent(parent) => parent.get_dist_file(project, dist).await,
+            };
+
+            match file {
+                Err(PackageError::NotFound) => continue,
+                file => return file,
+            }
+        }
+
+        Err(PackageError::NotFound)
+    }
+
+    async fn stream_dist_file(
+        &self,
+        file: &package::DistFile,
+        range: Option<Range<usize>>,
+    ) -> Result<BoxStream<'static, Result<Bytes, PackageError>>, PackageError> {
+        let file_path = Path::from(file.path.as_str());
+
+        let Some(range) = range else {
+            let content = self.store.get(&file_path).await.map_err(not_found)?;
+            return Ok(content.into_stream().map_err(PackageError::from).boxed());
+        };
+
+        // Ranges can be as large as the file, they are fetched by chunks.
+        let store = self.store.clone();
+        let content = futures::stream::try_unfold(range.start, move |start| {
+            let (store, file_path) = (store.clone(), file_path.clone());
+            async move {
+                if start >= range.end {
+                    return Ok(None);
+                }
+                let end = range.end.min(start + RANGE_CHUNK_SIZE);
+                let chunk = store.get_range(&file_path, start..end).await?;
+                Ok(Some((chunk, end)))
+            }
+        });
+
+        Ok(content.boxed())
+    }
+
+    async fn get_dist_url(&self, file: &package::DistFile) -> Result<Option<String>, PackageError> {
+        let Some(presigner) = &self.presigner else {
+            return Ok(None);
+        };
+
+        let file_path = Path::from(file.path.as_str());
+        Ok(Some(presigner.url(&file_path).await?))
+    }
+
+    async fn get_dist_metadata(
+        &self,
+        project: &str,
+        dist: &str,
+    ) -> Result<package::File, PackageError> {
+        let (sources, _) = self.sources(project).await?;
+
+        for source in sources {
+            let file = match source {
+                Source::Local => self.get_local_dist_metadata(project, dist).await,
+                Source::Parent(parent) => parent.get_dist_metadata(project, dist).await,
+            };
+
+            match file {
+                Err(PackageError::NotFound) => continue,
+                file => return file,
+            }
+        }
+
+        Err(PackageError::NotFound)
+    }
+
+    async fn yank_release(
+        &self,
+        project: &str,
+        version: &str,
+        yanked: bool,
+        reason: Option<&str>,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let project_id = self.get_project_id(project).await?;
+        self.check_maintainer(project_id, project, user).await?;
+
+        let reason = reason.filter(|_| yanked);
+        let canonical_version = version
+            .parse::<Version>()
+            .map(|v| v.canonical())
+            .unwrap_or_else(|_| version.to_string());
+
+        let mut tx = self.db.begin().await?;
+
+        let release = sqlx::query!(
+            r#"
+            UPDATE releases r
+            SET yanked = $3, yanked_reason = $4
+            FROM projects p
+            WHERE p.id = r.project_id
+                AND p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $5
+                AND r.canonical_version = $2
+            RETURNING p.name, r.version
+            "#,
+            project,
+            canonical_version,
+            yanked,
+            reason,
+            self.index_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(PackageError::NotFound)?;
+
+        let action = if yanked { "yank release" } else { "unyank release" };
+        add_journal_entry(
+            &mut *tx,
+            self.index_id,
+            &release.name,
+            Some(&release.version),
+            action,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn yank_file(
+        &self,
+        project: &str,
+        filename: &str,
+        yanked: bool,
+        reason: Option<&str>,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let project_id = self.get_project_id(project).await?;
+        self.check_maintainer(project_id, project, user).await?;
+
+        let reason = reason.filter(|_| yanked);
+
+        let mut tx = self.db.begin().await?;
+
+        let file = sqlx::query!(
+            r#"
+            UPDATE release_files rf
+            SET yanked = $3, yanked_reason = $4
+            FROM releases r
+            JOIN projects p ON p.id = r.project_id
+            WHERE r.id = rf.release_id
+                AND p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $5
+                AND rf.filename = $2
+            RETURNING p.name, r.version
+            "#,
+            project,
+            filename,
+            yanked,
+            reason,
+            self.index_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(PackageError::NotFound)?;
+
+        let action = if yanked { "yank file" } else { "unyank file" };
+        add_journal_entry(
+            &mut *tx,
+            self.index_id,
+            &file.name,
+            Some(&file.version),
+            &format!("{} {}", action, filename),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_overwrite_policy(&self, project: &str) -> Result<bool, PackageError> {
+        let project = sqlx::query!(
+            r#"
+            SELECT allow_file_overwrite
+            FROM projects
+            WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $2
+            "#,
+            project,
+            self.index_id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        project
+            .map(|p| p.allow_file_overwrite)
+            .ok_or(PackageError::NotFound)
+    }
+
+    async fn set_overwrite_policy(&self, project: &str, allow: bool) -> Result<(), PackageError> {
+        let query = sqlx::query!(
+            r#"
+            UPDATE projects
+            SET allow_file_overwrite = $2
+            WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $3
+            "#,
+            project,
+            allow,
+            self.index_id,
+        )
+        .execute(&self.db)
+        .await;
+
+        match query {
+            Ok(result) if result.rows_affected() > 0 => Ok(()),
+            Ok(_) => Err(PackageError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_upstream_policy(&self, project: &str) -> Result<UpstreamPolicy, PackageError> {
+        let project = sqlx::query!(
+            r#"
+            SELECT upstream_policy as "upstream_policy: UpstreamPolicy"
+            FROM projects
+            WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $2
+            "#,
+            project,
+            self.index_id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        project
+            .map(|p| p.upstream_policy)
+            .ok_or(PackageError::NotFound)
+    }
+
+    async fn set_upstream_policy(
+        &self,
+        project: &str,
+        policy: UpstreamPolicy,
+    ) -> Result<(), PackageError> {
+        let mut tx = self.db.begin().await?;
+        self.get_or_create_project(&mut tx, project, policy).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE projects
+            SET upstream_policy = $2
+            WHERE normalized_name = normalize_pep426_name($1)
+                AND index_id = $3
+            "#,
+            project,
+            policy as _,
+            self.index_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Set the upstream policy of {} to {:?}", project, policy);
+
+        Ok(())
+    }
+
+    async fn get_index(&self, name: &str) -> Result<SimpleState, PackageError> {
+        let index = sqlx::query!("SELECT id FROM indexes WHERE name = $1", name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or(PackageError::NotFound)?;
+
+        Ok(Arc::new(self.scoped(index.id, name)))
+    }
+
+    async fn get_index_definition(&self, name: &str) -> Result<IndexDefinition, PackageError> {
+        let index = sqlx::query!("SELECT id FROM indexes WHERE name = $1", name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or(PackageError::NotFound)?;
+        let store = self.scoped(index.id, name);
+
+        let overrides = sqlx::query!(
+            r#"
+            SELECT o.normalized_name, o.rule as "rule: RuleKind", i.name as "pinned_index?"
+            FROM index_overrides o
+            LEFT JOIN indexes i ON i.id = o.pinned_index_id
+            WHERE o.index_id = $1
+            ORDER BY o.normalized_name ASC
+            "#,
+            index.id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(IndexDefinition {
+            parents: store
+                .get_parents()
+                .await?
+                .into_iter()
+                .map(|parent| parent.index)
+                .collect(),
+            overrides: overrides
+                .into_iter()
+                .map(|o| ProjectOverride {
+                    project: o.normalized_name,
+                    rule: index_rule(o.rule, o.pinned_index),
+                })
+                .collect(),
+        })
+    }
+
+    async fn set_index_definition(
+        &self,
+        name: &str,
+        definition: &IndexDefinition,
+    ) -> Result<(), PackageError> {
+        definition.validate(name)?;
+
+        let mut tx = self.db.begin().await?;
+        let index = sqlx::query!(
+            r#"
+            INSERT INTO indexes (name)
+            VALUES ($1)
+            ON CONFLICT(name) DO UPDATE
+            SET name = EXCLUDED.name
+            RETURNING id
+            "#,
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let known = sqlx::query!(
+            "SELECT id, name FROM indexes WHERE name = ANY($1)",
+            &definition.parents
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let find_id = |name: &str| known.iter().find(|i| i.name == name).map(|i| i.id);
+
+        let mut parents = Vec::with_capacity(definition.parents.len());
+        for parent in &definition.parents {
+            let id = find_id(parent)
+                .ok_or_else(|| PackageError::InvalidIndex(format!("unknown index {}", parent)))?;
+            parents.push(id);
+        }
+
+        // An index can't inherit from itself through its parents.
+        let cycle = sqlx::query!(
+            r#"
+            WITH RECURSIVE ancestors(id) AS (
+                SELECT unnest($1::int[])
+                UNION
+                SELECT ip.parent_id
+                FROM index_parents ip
+                JOIN ancestors a ON ip.index_id = a.id
+            )
+            SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2) as "cycle!"
+            "#,
+            &parents,
+            index.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if cycle.cycle {
+            return Err(PackageError::InvalidIndex(format!(
+                "{} would inherit from itself",
+                name
+            )));
+        }
+
+        sqlx::query!("DELETE FROM index_parents WHERE index_id = $1", index.id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO index_parents (index_id, parent_id, priority)
+            SELECT $1, parent_id, priority::int
+            FROM UNNEST($2::int[]) WITH ORDINALITY AS p(parent_id, priority)
+            "#,
+            index.id,
+            &parents
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut projects = Vec::with_capacity(definition.overrides.len());
+        let mut rules = Vec::with_capacity(definition.overrides.len());
+        let mut pinned = Vec::with_capacity(definition.overrides.len());
+        for project_override in &definition.overrides {
+            projects.push(project_override.project.to_owned());
+            let (rule, pinned_index) = match &project_override.rule {
+                IndexRule::Pin { index: pin } if pin == name => (RuleKind::Pin, Some(index.id)),
+                IndexRule::Pin { index: pin } => (RuleKind::Pin, find_id(pin)),
+                IndexRule::Merge => (RuleKind::Merge, None),
+                IndexRule::Block => (RuleKind::Block, None),
+            };
+            rules.push(rule);
+            pinned.push(pinned_index);
+        }
+
+        sqlx::query!("DELETE FROM index_overrides WHERE index_id = $1", index.id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO index_overrides (index_id, normalized_name, rule, pinned_index_id)
+            SELECT $1, normalize_pep426_name(project), rule, pinned_index_id
+            FROM UNNEST($2::text[], $3::"index_rule"[], $4::int[])
+                AS o(project, rule, pinned_index_id)
+            "#,
+            index.id,
+            &projects,
+            &rules as _,
+            &pinned as _,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Define index {} with parents {:?}", name, definition.parents);
+
+        Ok(())
+    }
+
+    async fn promote_release(
+        &self,
+        project: &str,
+        version: &str,
+        target: &str,
+        user: &User,
+    ) -> Result<Promotion, PackageError> {
+        let promoted_by = &user.username;
+
+        if target == self.index {
+            return Err(PackageError::InvalidIndex(format!(
+                "{} can't be promoted to its own index",
+                project
+            )));
+        }
+
+        let target_index = sqlx::query!("SELECT id FROM indexes WHERE name = $1", target)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or(PackageError::NotFound)?;
+        let target_store = self.scoped(target_index.id, target);
+
+        let canonical_version = version
+            .parse::<Version>()
+            .map(|v| v.canonical())
+            .unwrap_or_else(|_| version.to_string());
+
+        let release = sqlx::query!(
+            r#"
+            SELECT r.id, r.version, p.id as project_id, p.name
+            FROM releases r
+            JOIN projects p ON p.id = r.project_id
+            WHERE p.normalized_name = normalize_pep426_name($1)
+                AND p.index_id = $2
+                AND r.canonical_version = $3
+            "#,
+            project,
+            self.index_id,
+            canonical_version,
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(PackageError::NotFound)?;
+        self.check_maintainer(release.project_id, &release.name, user)
+            .await?;
+
+        // The release lands in the target index like an upload would, a
+        // target project created by the promotion gets the maintainers of
+        // the source.
+        target_store.check_upload_policy(&release.name).await?;
+
+        let mut tx = self.db.begin().await?;
+        let (project_id, created) = target_store
+            .get_or_create_project(&mut tx, &release.name, UpstreamPolicy::Internal)
+            .await?;
+        if created {
+            sqlx::query!(
+                r#"
+                INSERT INTO project_roles (project_id, user_id, role)
+                SELECT $1, user_id, role
+                FROM project_roles
+                WHERE project_id = $2
+                "#,
+                project_id,
+                release.project_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        } else {
+            target_store
+                .check_maintainer(project_id, &release.name, user)
+                .await?;
+        }
+
+        // The release is copied with its files, descriptions and
+        // dependencies, the copied files share the stored objects. The
+        // source index keeps serving the release.
+        let copy = sqlx::query!(
+            r#"
+            INSERT INTO releases (
+                version, canonical_version, is_prerelease, author, author_email, maintainer, maintainer_email, home_page, license, summary, keywords, platform, download_url, requires_python, yanked, yanked_reason, classifiers, project_urls, provides_extras, project_id
+            )
+            SELECT
+                version, canonical_version, is_prerelease, author, author_email, maintainer, maintainer_email, home_page, license, summary, keywords, platform, download_url, requires_python, yanked, yanked_reason, classifiers, project_urls, provides_extras, $2
+            FROM releases
+            WHERE id = $1
+            ON CONFLICT (project_id, canonical_version) DO NOTHING
+            RETURNING id
+            "#,
+            release.id,
+            project_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            PackageError::ReleaseExists(format!(
+                "{} {} in index {}",
+                release.name, release.version, target
+            ))
+        })?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO release_files (
+                python_version, requires_python, packagetype, filename, path, size, md5_digest, sha256_digest, blake2_256_digest, upload_time, metadata_file_sha256_digest, metadata_file_blake2_256_digest, yanked, yanked_reason, build_tag, python_tags, abi_tags, platform_tags, upstream_url, metadata_path, release_id
+            )
+            SELECT
+                python_version, requires_python, packagetype, filename, path, size, md5_digest, sha256_digest, blake2_256_digest, upload_time, metadata_file_sha256_digest, metadata_file_blake2_256_digest, yanked, yanked_reason, build_tag, python_tags, abi_tags, platform_tags, upstream_url, metadata_path, $2
+            FROM release_files
+            WHERE release_id = $1
+            "#,
+            release.id,
+            copy.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO release_descriptions (content_type, raw, html, release_id)
+            SELECT content_type, raw, html, $2
+            FROM release_descriptions
+            WHERE release_id = $1
+            "#,
+            release.id,
+            copy.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO release_dependencies (
+                kind, specifier, name, extras, version_specifier, url, marker, release_id
+            )
+            SELECT kind, specifier, name, extras, version_specifier, url, marker, $2
+            FROM release_dependencies
+            WHERE release_id = $1
+            ORDER BY id
+            "#,
+            release.id,
+            copy.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let files = sqlx::query!(
+            "SELECT filename FROM release_files WHERE release_id = $1 ORDER BY filename",
+            release.id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let filenames: Vec<String> = files.into_iter().map(|f| f.filename).collect();
+
+        let promotion = sqlx::query!(
+            r#"
+            INSERT INTO promotions (
+                project, version, source_index_id, target_index_id, filenames, promoted_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING to_char(promoted_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"') as "promoted_at!"
+            "#,
+            &release.name,
+            &release.version,
+            self.index_id,
+            target_index.id,
+            &filenames,
+            promoted_by,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        add_journal_entry(
+            &mut *tx,
+            target_index.id,
+            &release.name,
+            Some(&release.version),
+            &format!("promote from {} to {}", self.index, target),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            "{} promoted {} {} from {} to {}",
+            promoted_by,
+            release.name,
+            release.version,
+            self.index,
+            target
+        );
+
+        Ok(Promotion {
+            project: release.name,
+            version: release.version,
+            source: self.index.to_owned(),
+            target: target.to_owned(),
+            filenames,
+            promoted_by: promoted_by.to_owned(),
+            promoted_at: promotion.promoted_at,
+        })
+    }
+
+    async fn get_promotions(&self, project: &str) -> Result<Vec<Promotion>, PackageError> {
+        let promotions = sqlx::query_as!(
+            Promotion,
+            r#"
+            SELECT
+                pr.project,
+                pr.version,
+                s.name as source,
+                t.name as target,
+                pr.filenames,
+                pr.promoted_by,
+                to_char(pr.promoted_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"') as "promoted_at!"
+            FROM promotions pr
+            JOIN indexes s ON s.id = pr.source_index_id
+            JOIN indexes t ON t.id = pr.target_index_id
+            WHERE normalize_pep426_name(pr.project) = normalize_pep426_name($1)
+                AND $2 IN (pr.source_index_id, pr.target_index_id)
+            ORDER BY pr.id DESC
+            "#,
+            project,
+            self.index_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(promotions)
+    }
+
+    async fn get_maintainers(&self, project: &str) -> Result<Vec<Maintainer>, PackageError> {
+        let project_id = self.get_project_id(project).await?;
+
+        let maintainers = sqlx::query_as!(
+            Maintainer,
+            r#"
+            SELECT u.username, pr.role as "role: ProjectRole"
+            FROM project_roles pr
+            JOIN users u ON u.id = pr.user_id
+            WHERE pr.project_id = $1
+            ORDER BY pr.role, u.username
+            "#,
+            project_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(maintainers)
+    }
+
+    async fn add_maintainer(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let project_id = self.get_project_id(project).await?;
+        self.check_owner(project_id, project, user).await?;
+        let user_id = self.find_user_id(username).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_roles (project_id, user_id, role)
+            VALUES ($1, $2, 'maintainer')
+            ON CONFLICT DO NOTHING
+            "#,
+            project_id,
+            user_id,
+        )
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(
+            "{} added {} as maintainer of {}",
+            user.username,
+            username,
+            project
+        );
+
+        Ok(())
+    }
+
+    async fn remove_maintainer(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let project_id = self.get_project_id(project).await?;
+        if username != user.username {
+            self.check_owner(project_id, project, user).await?;
+        }
+        let user_id = self.find_user_id(username).await?;
+
+        let role = sqlx::query!(
+            r#"
+            SELECT role as "role: ProjectRole"
+            FROM project_roles
+            WHERE project_id = $1 AND user_id = $2
+            "#,
+            project_id,
+            user_id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        // The owner is only replaced through a transfer, a project keeps its owner.
+        match role.map(|r| r.role) {
+            Some(ProjectRole::Owner) => {
+                return Err(PackageError::InvalidMaintainer(format!(
+                    "{} owns {}, transfer the ownership first",
+                    username, project
+                )))
+            }
+            Some(ProjectRole::Maintainer) => {}
+            None => return Err(PackageError::NotFound),
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM project_roles
+            WHERE project_id = $1 AND user_id = $2 AND role = 'maintainer'
+            "#,
+            project_id,
+            user_id,
+        )
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(
+            "{} removed {} from the maintainers of {}",
+            user.username,
+            username,
+            project
+        );
+
+        Ok(())
+    }
+
+    async fn transfer_ownership(
+        &self,
+        project: &str,
+        username: &str,
+        user: &User,
+    ) -> Result<(), PackageError> {
+        let project_id = self.get_project_id(project).await?;
+        self.check_owner(project_id, project, user).await?;
+        let user_id = self.find_user_id(username).await?;
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE project_roles
+            SET role = 'maintainer'
+            WHERE project_id = $1 AND role = 'owner'
+            "#,
+            project_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_roles (project_id, user_id, role)
+            VALUES ($1, $2, 'owner')
+            ON CONFLICT (project_id, user_id) DO UPDATE SET role = 'owner'
+            "#,
+            project_id,
+            user_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("{} transferred {} to {}", user.username, project, username);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::aws::AmazonS3Builder;
+
+    #[tokio::test]
+    async fn presigned_urls_include_the_prefix() {
+        let s3 = AmazonS3Builder::new()
+            .with_bucket_name("nest")
+            .with_region("us-east-1")
+            .with_access_key_id("key")
+            .with_secret_access_key("secret")
+            .build()
+            .unwrap();
+        let presigner = Presigner::new(Arc::new(s3), Some("index"), Duration::from_secs(300));
+
+        let path = Path::from_iter(["simple-index", "demo", "demo-0.1.0.tar.gz"]);
+        let url = presigner.url(&path).await.unwrap();
+
+        assert!(url.starts_with(
+            "https://s3.us-east-1.amazonaws.com/nest/index/simple-index/demo/demo-0.1.0.tar.gz?"
+        ));
+        assert!(url.contains("X-Amz-Expires=300"));
     }
 }
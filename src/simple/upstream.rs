@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::StreamExt;
+use reqwest::{header, StatusCode, Url};
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+use super::hashes::{verify_digest, DigestError, Hasher};
+use super::package::{normalize_project_name, DistHashes, PackageType};
+use super::wheel::WheelFilename;
+
+// PEP 691 - Content type of the JSON simple API.
+const SIMPLE_JSON: &str = "application/vnd.pypi.simple.v1+json";
+
+// Serial of the last change of a project, sent by PyPI and its mirrors.
+const LAST_SERIAL: &str = "X-PyPI-Last-Serial";
+
+// A stalled upstream index must not hold the requests of the clients. Pages
+// are fetched in full within the timeout, the list of every project is much
+// larger. Downloads may take long, only their stalls are timed out.
+const PAGE_TIMEOUT: Duration = Duration::from_secs(30);
+const LIST_TIMEOUT: Duration = Duration::from_secs(300);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpstreamError {
+    #[error("Invalid upstream index URL {0}.")]
+    InvalidUrl(String),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Digest(#[from] DigestError),
+
+    #[error("The upstream index didn't answer in time ({0}).")]
+    Timeout(Url),
+}
+
+#[derive(Deserialize)]
+struct ProjectList {
+    projects: Vec<ProjectListEntry>,
+}
+
+#[derive(Deserialize)]
+struct ProjectListEntry {
+    name: String,
+    #[serde(rename = "_last-serial")]
+    last_serial: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ProjectPage {
+    #[serde(default)]
+    meta: ProjectPageMeta,
+    files: Vec<ProjectPageFile>,
+}
+
+#[derive(Default, Deserialize)]
+struct ProjectPageMeta {
+    #[serde(rename = "_last-serial")]
+    last_serial: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ProjectPageFile {
+    filename: String,
+    url: String,
+    hashes: HashMap<String, String>,
+    #[serde(rename = "requires-python")]
+    requires_python: Option<String>,
+    size: Option<u64>,
+    #[serde(default)]
+    yanked: Yanked,
+}
+
+// PEP 592 - `yanked` is either a boolean or the reason of the yank.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Yanked {
+    Flag(bool),
+    Reason(String),
+}
+
+impl Default for Yanked {
+    fn default() -> Self {
+        Yanked::Flag(false)
+    }
+}
+
+// Project listed by the upstream index, indexes which don't track changes
+// have no serial.
+pub struct UpstreamProject {
+    pub name: String,
+    pub last_serial: Option<i64>,
+}
+
+// Files of a project, with the serial of the last change of the project.
+pub struct UpstreamFiles {
+    pub files: Vec<UpstreamFile>,
+    pub last_serial: Option<i64>,
+}
+
+// File listed by the upstream index.
+pub struct UpstreamFile {
+    pub filename: String,
+    pub url: Url,
+    pub sha256_digest: String,
+    pub requires_python: Option<String>,
+    pub size: Option<u64>,
+    pub yanked: bool,
+    pub yanked_reason: Option<String>,
+}
+
+impl UpstreamFile {
+    pub fn package_type(&self) -> Option<PackageType> {
+        PackageType::from_filename(&self.filename)
+    }
+
+    pub fn wheel(&self) -> Option<WheelFilename> {
+        self.filename.parse().ok()
+    }
+
+    // The version isn't listed by the simple API, it is read from the
+    // filename. Source distributions are `{name}-{version}.{ext}` and eggs
+    // are `{name}-{version}-{python}.egg`.
+    pub fn version(&self) -> Option<String> {
+        if let Some(wheel) = self.wheel() {
+            return Some(wheel.version);
+        }
+
+        if let Some(stem) = self.filename.strip_suffix(".egg") {
+            return stem.split('-').nth(1).map(str::to_owned);
+        }
+
+        [".tar.gz", ".tar.bz2", ".tar.xz", ".tgz", ".tar", ".zip"]
+            .iter()
+            .find_map(|ext| self.filename.strip_suffix(ext))
+            .and_then(|stem| stem.rsplit_once('-'))
+            .map(|(_, version)| version.to_owned())
+            .filter(|version| !version.is_empty())
+    }
+}
+
+// Downloaded file, spooled to disk like uploads.
+pub struct UpstreamContent {
+    pub content: NamedTempFile,
+    pub size: u64,
+    pub hashes: DistHashes,
+}
+
+// Client of the index the projects which aren't hosted by Nest are fetched
+// from, its files are cached on their first download.
+#[derive(Clone)]
+pub struct Upstream {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl Upstream {
+    pub fn new(url: &str) -> Result<Upstream, UpstreamError> {
+        // Project pages are joined to the index URL, it must end with a slash.
+        let url = match url.ends_with('/') {
+            true => Url::parse(url),
+            false => Url::parse(&format!("{}/", url)),
+        }
+        .map_err(|_| UpstreamError::InvalidUrl(url.to_owned()))?;
+
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("nest/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Upstream { client, url })
+    }
+
+    // Every project of the upstream index.
+    pub async fn get_projects(&self) -> Result<Vec<UpstreamProject>, UpstreamError> {
+        let list: ProjectList = self
+            .client
+            .get(self.url.clone())
+            .header(header::ACCEPT, SIMPLE_JSON)
+            .timeout(LIST_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(list
+            .projects
+            .into_iter()
+            .map(|p| UpstreamProject {
+                name: p.name,
+                last_serial: p.last_serial,
+            })
+            .collect())
+    }
+
+    pub async fn get_files(&self, project: &str) -> Result<Vec<UpstreamFile>, UpstreamError> {
+        Ok(self.get_project_files(project).await?.files)
+    }
+
+    // Files of the project, projects unknown to the upstream index have none.
+    pub async fn get_project_files(&self, project: &str) -> Result<UpstreamFiles, UpstreamError> {
+        let page_url = self
+            .url
+            .join(&format!("{}/", normalize_project_name(project)))
+            .map_err(|_| UpstreamError::InvalidUrl(project.to_owned()))?;
+
+        let response = self
+            .client
+            .get(page_url.clone())
+            .header(header::ACCEPT, SIMPLE_JSON)
+            .timeout(PAGE_TIMEOUT)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(UpstreamFiles {
+                files: Vec::new(),
+                last_serial: None,
+            });
+        }
+
+        let header_serial = response
+            .headers()
+            .get(LAST_SERIAL)
+            .and_then(|serial| serial.to_str().ok())
+            .and_then(|serial| serial.parse().ok());
+
+        let page: ProjectPage = response.error_for_status()?.json().await?;
+
+        // Files are only cached once their digest is checked, files without
+        // a sha256 digest are left out.
+        let files = page
+            .files
+            .into_iter()
+            .filter_map(|file| {
+                let url = page_url.join(&file.url).ok()?;
+                let sha256_digest = file.hashes.get("sha256")?.to_lowercase();
+                let (yanked, yanked_reason) = match file.yanked {
+                    Yanked::Flag(yanked) => (yanked, None),
+                    Yanked::Reason(reason) => (true, Some(reason)),
+                };
+
+                Some(UpstreamFile {
+                    filename: file.filename,
+                    url,
+                    sha256_digest,
+                    requires_python: file.requires_python.filter(|r| !r.is_empty()),
+                    size: file.size,
+                    yanked,
+                    yanked_reason,
+                })
+            })
+            .collect();
+
+        Ok(UpstreamFiles {
+            files,
+            last_serial: header_serial.or(page.meta.last_serial),
+        })
+    }
+
+    // The file is streamed to disk and rejected when its content doesn't
+    // match the digest listed by the upstream index.
+    pub async fn download(&self, file: &UpstreamFile) -> Result<UpstreamContent, UpstreamError> {
+        let timeout = || UpstreamError::Timeout(file.url.clone());
+        let response = tokio::time::timeout(READ_TIMEOUT, self.client.get(file.url.clone()).send())
+            .await
+            .map_err(|_| timeout())??
+            .error_for_status()?;
+
+        let content = NamedTempFile::new()?;
+        let mut writer = tokio::fs::File::from_std(content.reopen()?);
+        let mut hasher = Hasher::default();
+        let mut size = 0;
+
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = tokio::time::timeout(READ_TIMEOUT, stream.next())
+            .await
+            .map_err(|_| timeout())?
+        {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            writer.write_all(&chunk).await?;
+            size += chunk.len() as u64;
+        }
+        writer.flush().await?;
+
+        let (md5_digest, sha256_digest, blake2_256_digest) = hasher.finalize();
+        let sha256_digest = verify_digest("sha256", Some(&file.sha256_digest), sha256_digest)?;
+
+        Ok(UpstreamContent {
+            content,
+            size,
+            hashes: DistHashes {
+                md5_digest,
+                sha256_digest,
+                blake2_256_digest,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(filename: &str) -> UpstreamFile {
+        UpstreamFile {
+            filename: filename.to_owned(),
+            url: Url::parse("https://example.com/files/").unwrap(),
+            sha256_digest: String::new(),
+            requires_python: None,
+            size: None,
+            yanked: false,
+            yanked_reason: None,
+        }
+    }
+
+    #[test]
+    fn version_is_read_from_the_filename() {
+        assert_eq!(
+            file("demo_pkg-1.0-py3-none-any.whl").version().as_deref(),
+            Some("1.0")
+        );
+        assert_eq!(
+            file("python-dateutil-2.8.2.tar.gz").version().as_deref(),
+            Some("2.8.2")
+        );
+        assert_eq!(file("demo-0.1-py2.7.egg").version().as_deref(), Some("0.1"));
+        assert_eq!(file("demo.tar.gz").version(), None);
+    }
+
+    #[test]
+    fn project_pages_are_parsed() {
+        let page: ProjectPage = serde_json::from_str(
+            r#"{
+                "meta": {"api-version": "1.1"},
+                "name": "demo",
+                "files": [
+                    {"filename": "demo-1.0.tar.gz", "url": "../../files/demo-1.0.tar.gz", "hashes": {"sha256": "AB"}, "yanked": "broken"},
+                    {"filename": "demo-0.9.tar.gz", "url": "demo-0.9.tar.gz", "hashes": {}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(page.files.len(), 2);
+        assert!(matches!(&page.files[0].yanked, Yanked::Reason(r) if r == "broken"));
+        assert!(matches!(page.files[1].yanked, Yanked::Flag(false)));
+    }
+}
@@ -0,0 +1,400 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+// PEP 440 - Version Identification and Dependency Specification
+// https://packaging.python.org/en/latest/specifications/version-specifiers/
+//
+// Same pattern as `packaging.version.VERSION_PATTERN`, it accepts every
+// spelling allowed by the normalization rules of the specification.
+const VERSION_PATTERN: &str = r"(?xi)
+    ^\s*v?
+    (?:(?P<epoch>[0-9]+)!)?
+    (?P<release>[0-9]+(?:\.[0-9]+)*)
+    (?P<pre>
+        [-_\.]?
+        (?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)
+        [-_\.]?
+        (?P<pre_n>[0-9]+)?
+    )?
+    (?P<post>
+        (?:-(?P<post_n1>[0-9]+))
+        |
+        (?:
+            [-_\.]?
+            (?P<post_l>post|rev|r)
+            [-_\.]?
+            (?P<post_n2>[0-9]+)?
+        )
+    )?
+    (?P<dev>
+        [-_\.]?
+        (?P<dev_l>dev)
+        [-_\.]?
+        (?P<dev_n>[0-9]+)?
+    )?
+    (?:\+(?P<local>[a-z0-9]+(?:[-_\.][a-z0-9]+)*))?
+    \s*$";
+
+fn version_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(VERSION_PATTERN).unwrap())
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid version {0}, versions must follow PEP 440.")]
+pub struct InvalidVersion(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Alpha,
+    Beta,
+    ReleaseCandidate,
+}
+
+impl PreRelease {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PreRelease::Alpha => "a",
+            PreRelease::Beta => "b",
+            PreRelease::ReleaseCandidate => "rc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalSegment {
+    Number(u64),
+    String(String),
+}
+
+impl Ord for LocalSegment {
+    // Numeric segments always sort after alphanumeric ones.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LocalSegment::Number(a), LocalSegment::Number(b)) => a.cmp(b),
+            (LocalSegment::String(a), LocalSegment::String(b)) => a.cmp(b),
+            (LocalSegment::Number(_), LocalSegment::String(_)) => Ordering::Greater,
+            (LocalSegment::String(_), LocalSegment::Number(_)) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for LocalSegment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for LocalSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalSegment::Number(n) => write!(f, "{}", n),
+            LocalSegment::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreRelease, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalSegment>,
+}
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidVersion(version.to_string());
+
+        let captures = version_regex().captures(version).ok_or_else(invalid)?;
+
+        let number = |name: &str| -> Result<Option<u64>, InvalidVersion> {
+            captures
+                .name(name)
+                .map(|m| m.as_str().parse::<u64>().map_err(|_| invalid()))
+                .transpose()
+        };
+
+        let epoch = number("epoch")?.unwrap_or(0);
+
+        let release = captures["release"]
+            .split('.')
+            .map(|n| n.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<u64>, InvalidVersion>>()?;
+
+        let pre = match captures.name("pre_l") {
+            Some(label) => {
+                let kind = match label.as_str().to_lowercase().as_str() {
+                    "a" | "alpha" => PreRelease::Alpha,
+                    "b" | "beta" => PreRelease::Beta,
+                    _ => PreRelease::ReleaseCandidate,
+                };
+                Some((kind, number("pre_n")?.unwrap_or(0)))
+            }
+            None => None,
+        };
+
+        let post = match captures.name("post") {
+            Some(_) => Some(number("post_n1")?.or(number("post_n2")?).unwrap_or(0)),
+            None => None,
+        };
+
+        let dev = match captures.name("dev") {
+            Some(_) => Some(number("dev_n")?.unwrap_or(0)),
+            None => None,
+        };
+
+        let local = captures
+            .name("local")
+            .map(|local| {
+                local
+                    .as_str()
+                    .split(['-', '_', '.'])
+                    .map(|segment| match segment.parse::<u64>() {
+                        Ok(n) => LocalSegment::Number(n),
+                        Err(_) => LocalSegment::String(segment.to_lowercase()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Version {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+impl Version {
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    pub fn is_local(&self) -> bool {
+        !self.local.is_empty()
+    }
+
+    // Version without its local segment.
+    pub fn public(&self) -> Version {
+        Version {
+            local: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    // Trailing zeros of the release segment don't change the version.
+    fn release_without_trailing_zeros(&self) -> &[u64] {
+        let len = self
+            .release
+            .iter()
+            .rposition(|n| *n != 0)
+            .map_or(1, |i| i + 1);
+
+        &self.release[..len.min(self.release.len())]
+    }
+
+    // Same as `packaging.utils.canonicalize_version`: the normalized version
+    // with the trailing zeros of the release segment stripped.
+    pub fn canonical(&self) -> String {
+        let stripped = Version {
+            release: self.release_without_trailing_zeros().to_vec(),
+            ..self.clone()
+        };
+
+        stripped.to_string()
+    }
+
+    // Sort key of the pre/post/dev segments, as in `packaging.version._cmpkey`.
+    fn suffix_key(&self) -> (i8, Option<(PreRelease, u64)>, i8, u64, i8, u64) {
+        // A dev release without pre or post release sorts before pre-releases.
+        let (pre_rank, pre) = match (&self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (-1, None),
+            (None, _, _) => (1, None),
+            (Some(pre), _, _) => (0, Some(*pre)),
+        };
+
+        let (post_rank, post) = match self.post {
+            None => (-1, 0),
+            Some(post) => (0, post),
+        };
+
+        let (dev_rank, dev) = match self.dev {
+            None => (1, 0),
+            Some(dev) => (0, dev),
+        };
+
+        (pre_rank, pre, post_rank, post, dev_rank, dev)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| {
+                self.release_without_trailing_zeros()
+                    .cmp(other.release_without_trailing_zeros())
+            })
+            .then_with(|| self.suffix_key().cmp(&other.suffix_key()))
+            .then_with(|| self.local.cmp(&other.local))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+// Normalized form of the version.
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+
+        let release: Vec<String> = self.release.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", release.join("."))?;
+
+        if let Some((kind, n)) = &self.pre {
+            write!(f, "{}{}", kind.as_str(), n)?;
+        }
+
+        if let Some(post) = self.post {
+            write!(f, ".post{}", post)?;
+        }
+
+        if let Some(dev) = self.dev {
+            write!(f, ".dev{}", dev)?;
+        }
+
+        if self.is_local() {
+            let local: Vec<String> = self.local.iter().map(|s| s.to_string()).collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+
+        Ok(())
+    }
+}
+
+// PEP 440 equality between two version strings, `1.0` and `1.0.0` are the
+// same version. Invalid versions are compared as plain strings.
+pub fn same_version(a: &str, b: &str) -> bool {
+    match (a.parse::<Version>(), b.parse::<Version>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim() == b.trim(),
+    }
+}
+
+// Highest final release of a list of versions, pre-releases are only picked
+// when there is no final release. Invalid versions are ignored.
+pub fn latest_version<'a>(versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    versions
+        .into_iter()
+        .filter_map(|v| v.parse::<Version>().ok().map(|parsed| (parsed, v)))
+        .max_by(|(a, _), (b, _)| {
+            (!a.is_prerelease())
+                .cmp(&!b.is_prerelease())
+                .then_with(|| a.cmp(b))
+        })
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{latest_version, same_version, Version};
+
+    fn v(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn normalize_alternative_spellings() {
+        assert_eq!(v("1.0-ALPHA.1").to_string(), "1.0a1");
+        assert_eq!(v("v1.0.0-preview2").to_string(), "1.0.0rc2");
+        assert_eq!(v("1.0-1").to_string(), "1.0.post1");
+        assert_eq!(v("1.0.rev").to_string(), "1.0.post0");
+        assert_eq!(v("1.0-dev").to_string(), "1.0.dev0");
+        assert_eq!(v("1!2.0+Ubuntu-1").to_string(), "1!2.0+ubuntu.1");
+    }
+
+    #[test]
+    fn canonical_version_strips_trailing_zeros() {
+        assert_eq!(v("1.0.0").canonical(), "1");
+        assert_eq!(v("1.10.0rc1").canonical(), "1.10rc1");
+        assert_eq!(v("0.0").canonical(), "0");
+        assert_eq!(v("2!1.0.post1").canonical(), "2!1.post1");
+        assert_eq!(v("1.0.0-rc1").canonical(), "1rc1");
+        assert_eq!(v("1.0-1").canonical(), "1.post1");
+    }
+
+    #[test]
+    fn order_follows_pep_440() {
+        let ordered = [
+            "1.0.dev0",
+            "1.0a1.dev1",
+            "1.0a1",
+            "1.0b2.post1",
+            "1.0rc1",
+            "1.0",
+            "1.0+local.1",
+            "1.0+local.2",
+            "1.0.post1.dev1",
+            "1.0.post1",
+            "1.9",
+            "1.10",
+            "1!0.1",
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(same_version("1.0", "1.0.0"));
+        assert!(same_version("1.0-alpha1", "1.0a1"));
+        assert!(!same_version("1.0", "1.0.post0"));
+    }
+
+    #[test]
+    fn detect_prereleases() {
+        assert!(v("1.0rc1").is_prerelease());
+        assert!(v("1.0.dev3").is_prerelease());
+        assert!(!v("1.0.post1").is_prerelease());
+        assert!(!v("1.0+cpu").is_prerelease());
+    }
+
+    #[test]
+    fn latest_version_prefers_final_releases() {
+        assert_eq!(latest_version(["1.9", "1.10", "2.0rc1"]), Some("1.10"));
+        assert_eq!(latest_version(["1.0a1", "1.0b1"]), Some("1.0b1"));
+        assert_eq!(latest_version(["not-a-version"]), None);
+    }
+
+    #[test]
+    fn reject_invalid_versions() {
+        assert!("1.0-foo".parse::<Version>().is_err());
+        assert!("latest".parse::<Version>().is_err());
+        assert!("1..0".parse::<Version>().is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::package::normalize_project_name;
+use super::version::same_version;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WheelError {
+    #[error("Invalid wheel filename {0}: expected {{name}}-{{version}}(-{{build}})?-{{python}}-{{abi}}-{{platform}}.whl.")]
+    InvalidFilename(String),
+
+    #[error("Invalid build tag {0}: it must start with a digit.")]
+    InvalidBuildTag(String),
+
+    #[error("Unsupported platform tag {0}.")]
+    InvalidPlatformTag(String),
+
+    #[error("The {field} of the wheel filename ({filename}) does not match the {field} of the distribution metadata ({metadata}).")]
+    Mismatch {
+        field: &'static str,
+        filename: String,
+        metadata: String,
+    },
+
+    #[error("The tags of the wheel filename don't match the tags declared in the WHEEL file: {0}.")]
+    TagsMismatch(String),
+}
+
+const LINUX_PLATFORM_PATTERN: &str = r"^(manylinux1|manylinux2010|manylinux2014|manylinux_\d+_\d+|musllinux_\d+_\d+)_(x86_64|i686|aarch64|armv7l|ppc64|ppc64le|s390x|riscv64|loongarch64)$";
+
+fn linux_platform_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(LINUX_PLATFORM_PATTERN).unwrap())
+}
+
+// PEP 427 - The Wheel Binary Package Format
+// https://packaging.python.org/en/latest/specifications/binary-distribution-format/
+//
+// `{distribution}-{version}(-{build tag})?-{python tag}-{abi tag}-{platform tag}.whl`
+//
+// Each tag may be a compressed tag set (e.g. `py2.py3`), tag sets are expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WheelFilename {
+    pub name: String,
+    pub version: String,
+    pub build_tag: Option<String>,
+    pub python_tags: Vec<String>,
+    pub abi_tags: Vec<String>,
+    pub platform_tags: Vec<String>,
+}
+
+impl FromStr for WheelFilename {
+    type Err = WheelError;
+
+    fn from_str(filename: &str) -> Result<Self, Self::Err> {
+        let invalid = || WheelError::InvalidFilename(filename.to_string());
+
+        let stem = filename
+            .strip_suffix(".whl")
+            .or_else(|| filename.strip_suffix(".WHL"))
+            .ok_or_else(invalid)?;
+
+        let parts: Vec<&str> = stem.split('-').collect();
+
+        let (name, version, build_tag, python, abi, platform) = match parts[..] {
+            [name, version, python, abi, platform] => (name, version, None, python, abi, platform),
+            [name, version, build, python, abi, platform] => {
+                if !build.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(WheelError::InvalidBuildTag(build.to_string()));
+                }
+                (name, version, Some(build.to_string()), python, abi, platform)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let tag_set = |tags: &str| -> Result<Vec<String>, WheelError> {
+            let tags: Vec<String> = tags.split('.').map(str::to_string).collect();
+            if tags.iter().any(|t| t.is_empty()) {
+                return Err(invalid());
+            }
+            Ok(tags)
+        };
+
+        if name.is_empty() || version.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(WheelFilename {
+            name: name.to_string(),
+            version: version.to_string(),
+            build_tag,
+            python_tags: tag_set(python)?,
+            abi_tags: tag_set(abi)?,
+            platform_tags: tag_set(platform)?,
+        })
+    }
+}
+
+impl WheelFilename {
+    // Expanded `{python}-{abi}-{platform}` tag triples.
+    pub fn tags(&self) -> BTreeSet<String> {
+        let mut tags = BTreeSet::new();
+
+        for python in &self.python_tags {
+            for abi in &self.abi_tags {
+                for platform in &self.platform_tags {
+                    tags.insert(format!("{}-{}-{}", python, abi, platform));
+                }
+            }
+        }
+
+        tags
+    }
+
+    pub fn check_metadata(&self, name: &str, version: &str) -> Result<(), WheelError> {
+        if normalize_project_name(&self.name) != normalize_project_name(name) {
+            return Err(WheelError::Mismatch {
+                field: "name",
+                filename: self.name.to_owned(),
+                metadata: name.to_string(),
+            });
+        }
+
+        if !same_version(&self.version, version) {
+            return Err(WheelError::Mismatch {
+                field: "version",
+                filename: self.version.to_owned(),
+                metadata: version.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // `manylinux` and `musllinux` tags must be well formed (PEP 513, 599, 600, 656).
+    pub fn check_platform_tags(&self) -> Result<(), WheelError> {
+        let linux = linux_platform_regex();
+
+        for tag in &self.platform_tags {
+            let is_portable_linux = tag.starts_with("manylinux") || tag.starts_with("musllinux");
+
+            if is_portable_linux && !linux.is_match(tag) {
+                return Err(WheelError::InvalidPlatformTag(tag.to_owned()));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The `Tag` lines of the `WHEEL` file must describe the same tags as the
+    // filename, this catches wheels that were renamed after being built.
+    pub fn check_wheel_file(&self, wheel_file: &str) -> Result<(), WheelError> {
+        let declared: BTreeSet<String> = wheel_file
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| key.trim().eq_ignore_ascii_case("tag"))
+            .map(|(_, value)| value.trim().to_string())
+            .collect();
+
+        if declared.is_empty() {
+            return Ok(());
+        }
+
+        let tags = self.tags();
+
+        if declared != tags {
+            let difference: Vec<String> = tags.symmetric_difference(&declared).cloned().collect();
+            return Err(WheelError::TagsMismatch(difference.join(", ")));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WheelFilename;
+
+    #[test]
+    fn parse_compressed_tag_sets() {
+        let wheel: WheelFilename =
+            "MarkupSafe-2.1.5-cp310-cp310-manylinux_2_17_x86_64.manylinux2014_x86_64.whl"
+                .parse()
+                .unwrap();
+
+        assert_eq!(wheel.name, "MarkupSafe");
+        assert_eq!(wheel.version, "2.1.5");
+        assert_eq!(wheel.build_tag, None);
+        assert_eq!(wheel.python_tags, vec!["cp310"]);
+        assert_eq!(
+            wheel.platform_tags,
+            vec!["manylinux_2_17_x86_64", "manylinux2014_x86_64"]
+        );
+        assert_eq!(wheel.tags().len(), 2);
+        assert!(wheel.check_platform_tags().is_ok());
+    }
+
+    #[test]
+    fn parse_build_tag() {
+        let wheel: WheelFilename = "nest-0.1.0-1-py2.py3-none-any.whl".parse().unwrap();
+
+        assert_eq!(wheel.build_tag.as_deref(), Some("1"));
+        assert_eq!(wheel.python_tags, vec!["py2", "py3"]);
+
+        assert!("nest-0.1.0-b1-py3-none-any.whl"
+            .parse::<WheelFilename>()
+            .is_err());
+    }
+
+    #[test]
+    fn reject_invalid_filenames() {
+        assert!("nest-0.1.0.whl".parse::<WheelFilename>().is_err());
+        assert!("nest-0.1.0-py3-none-any.tar.gz"
+            .parse::<WheelFilename>()
+            .is_err());
+    }
+
+    #[test]
+    fn reject_malformed_manylinux_tags() {
+        let wheel: WheelFilename = "nest-0.1.0-cp310-cp310-linux_x86_64.whl".parse().unwrap();
+        assert!(wheel.check_platform_tags().is_ok());
+
+        let wheel: WheelFilename = "nest-0.1.0-cp310-cp310-manylinux_x86_64.whl"
+            .parse()
+            .unwrap();
+        assert!(wheel.check_platform_tags().is_err());
+    }
+
+    #[test]
+    fn wheel_file_tags_must_match_filename() {
+        let wheel: WheelFilename = "nest-0.1.0-py2.py3-none-any.whl".parse().unwrap();
+
+        let wheel_file = "Wheel-Version: 1.0\nRoot-Is-Purelib: true\nTag: py2-none-any\nTag: py3-none-any\n";
+        assert!(wheel.check_wheel_file(wheel_file).is_ok());
+
+        let wheel_file = "Wheel-Version: 1.0\nTag: cp310-cp310-linux_x86_64\n";
+        assert!(wheel.check_wheel_file(wheel_file).is_err());
+    }
+}
@@ -9,7 +9,6 @@ use axum_template::engine::Engine;
 
 use minijinja::{path_loader, Environment};
 use minijinja_autoreload::AutoReloader;
-use object_store::local::LocalFileSystem;
 use tokio::net::TcpListener as TokioTcpListener;
 
 use tokio::signal;
@@ -24,12 +23,14 @@ use tower_sessions::session_store::ExpiredDeletion;
 use tower_sessions_sqlx_store::PostgresStore;
 use tracing::Level;
 
-use crate::authentication::Backend;
+use crate::api;
+use crate::authentication::{bootstrap_admin, Backend};
 use crate::front;
 use crate::greeting;
 use crate::healthcheck::healthcheck;
+use crate::pypi;
 use crate::settings;
-use crate::simple::{self, store::Store};
+use crate::simple::{self, mirror::Mirror, store::Store, upstream::Upstream};
 use crate::state::AppState;
 use sqlx::postgres::PgPoolOptions;
 
@@ -37,17 +38,26 @@ pub struct Application {
     app: Router,
     session_store: PostgresStore,
     listener: TcpListener,
+    mirror: Option<Mirror>,
 }
 
 impl Application {
     pub async fn build(config: settings::Settings) -> Self {
-        std::fs::create_dir_all(config.persistence.object_storage.path.clone())
-            .expect("Unable to create folder for wheels");
-        let storage =
-            LocalFileSystem::new_with_prefix(config.persistence.object_storage.path.clone())
-                .expect("Unable to set up local index.");
-        let store = Arc::new(storage);
+        let storage = config
+            .persistence
+            .object_storage
+            .build()
+            .expect("Unable to set up the object storage.");
 
+        Self::build_with_storage(config, storage).await
+    }
+
+    // Tests provide their own object storage, to sign the URLs of an
+    // in-memory store.
+    pub async fn build_with_storage(
+        config: settings::Settings,
+        storage: settings::ObjectStorage,
+    ) -> Self {
         let db_pool = PgPoolOptions::new()
             .acquire_timeout(std::time::Duration::from_secs(2))
             .connect_lazy_with(config.persistence.database.with_db());
@@ -58,9 +68,36 @@ impl Application {
             .await
             .expect("Unable to run migrations");
 
-        let simple_store = Store::new(db_pool.clone(), store);
+        if let Some(admin) = &config.admin {
+            tracing::info!("Give the admin role to {}", &admin.username);
+            bootstrap_admin(&db_pool, &admin.username, &admin.password)
+                .await
+                .expect("Unable to bootstrap the admin account.");
+        }
+
+        let upstream = config.upstream.as_ref().map(|upstream| {
+            Upstream::new(&upstream.url).expect("Unable to set up the upstream index.")
+        });
+
+        let simple_store = Store::new(
+            db_pool.clone(),
+            storage.store,
+            storage.presigner,
+            upstream.clone(),
+        );
+        if let Err(e) = simple_store.backfill_canonical_versions().await {
+            tracing::error!("Unable to backfill the canonical versions: {}", e);
+        }
         let simple_store = Arc::new(simple_store);
 
+        let mirror = config.mirror.as_ref().map(|mirror| {
+            let upstream = upstream
+                .clone()
+                .expect("The mirror requires an upstream index.");
+            Mirror::new(simple_store.clone(), upstream, mirror)
+                .expect("Unable to set up the mirror.")
+        });
+
         let jinja = AutoReloader::new(move |notifier| {
             let template_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates");
 
@@ -71,7 +108,8 @@ impl Application {
             Ok(env)
         });
 
-        let key = Key::generate();
+        let key = Key::try_from(config.application.session_key.as_bytes())
+            .expect("The session key must be at least 64 bytes long.");
 
         let session_store = PostgresStore::new(db_pool.clone());
         session_store
@@ -96,6 +134,9 @@ impl Application {
         let app = Router::new()
             .nest("/", front::router())
             .nest("/simple", simple::router())
+            .nest("/indexes/:index/simple", simple::router())
+            .nest("/api", api::router())
+            .nest("/pypi", pypi::router())
             .layer(auth_layer)
             .with_state(app_state)
             .route("/healthcheck", get(healthcheck));
@@ -128,6 +169,7 @@ impl Application {
             app,
             session_store,
             listener,
+            mirror,
         }
     }
 
@@ -143,6 +185,10 @@ impl Application {
                 .continuously_delete_expired(tokio::time::Duration::from_secs(60)),
         );
 
+        if let Some(mirror) = self.mirror {
+            tokio::task::spawn(mirror.run());
+        }
+
         axum::serve(listener, app)
             .with_graceful_shutdown(shutdown_signal(deletion_task.abort_handle()))
             .await
@@ -1,35 +1,249 @@
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use flate2::{write::GzEncoder, Compression};
 use nest::settings;
+use nest::simple::store::Presigner;
 use nest::startup::Application;
+use object_store::{aws::AmazonS3Builder, memory::InMemory};
+use reqwest::multipart::{Form, Part};
+
+// Admin bootstrapped by every test application, it shares the password of
+// the other users.
+pub const ADMIN: &str = "admin";
+
 pub struct TestApp {
     pub address: String,
+    #[allow(dead_code)]
     pub port: u16,
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with_upstream(None).await
+}
+
+// Projects which aren't hosted are looked up on the upstream index.
+pub async fn spawn_app_with_upstream(upstream: Option<String>) -> TestApp {
+    spawn(upstream, None, None).await
+}
+
+// Downloads are redirected to URLs signed like the ones of an S3 bucket,
+// the files stay in memory.
+#[allow(dead_code)]
+pub async fn spawn_app_with_presigner() -> TestApp {
+    let signer = AmazonS3Builder::new()
+        .with_bucket_name("nest")
+        .with_region("us-east-1")
+        .with_access_key_id("key")
+        .with_secret_access_key("secret")
+        .build()
+        .expect("Unable to set up the signer.");
+    let presigner = Presigner::new(Arc::new(signer), None, Duration::from_secs(300));
+
+    spawn(None, None, Some(presigner)).await
+}
+
+// The listed projects of the upstream index are mirrored on startup.
+#[allow(dead_code)]
+pub async fn spawn_app_with_mirror(upstream: String, projects: Vec<String>) -> TestApp {
+    let mirror = settings::MirrorSettings {
+        projects,
+        exclude: Vec::new(),
+        interval: 3600,
+    };
+
+    spawn(Some(upstream), Some(mirror), None).await
+}
+
+fn database_settings() -> settings::DatabaseSettings {
+    settings::DatabaseSettings {
+        host: String::from("localhost"),
+        port: 5432,
+        username: String::from("nest-user"),
+        password: String::from("nest-secret"),
+        name: String::from("nest"),
+        require_ssl: false,
+    }
+}
+
+// Direct access to the database of the test applications, to set up states
+// the API can't reach.
+pub async fn connect_db() -> sqlx::PgPool {
+    sqlx::PgPool::connect_with(database_settings().with_db())
+        .await
+        .expect("Failed to connect to the database.")
+}
+
+async fn spawn(
+    upstream: Option<String>,
+    mirror: Option<settings::MirrorSettings>,
+    presigner: Option<Presigner>,
+) -> TestApp {
     let configuration = settings::Settings {
         application: settings::ApplicationSettings {
             host: String::from("127.0.0.1"),
             port: 0,
+            session_key: "0123456789abcdef".repeat(4),
         },
         persistence: settings::PersistenceSettings {
-            object_storage: settings::ObjectStorageSettings {
-                path: String::from("./simple-index"),
-            },
-            database: settings::DatabaseSettings {
-                host: String::from("localhost"),
-                port: 5432,
-                username: String::from("nest-user"),
-                password: String::from("nest-secret"),
-                name: String::from("nest"),
-                require_ssl: false,
-            },
+            object_storage: settings::ObjectStorageSettings::Memory,
+            database: database_settings(),
         },
+        upstream: upstream.map(|url| settings::UpstreamSettings { url }),
+        mirror,
+        admin: Some(settings::AdminSettings {
+            username: String::from(ADMIN),
+            password: String::from("secret"),
+        }),
+    };
+    let storage = settings::ObjectStorage {
+        store: Arc::new(InMemory::new()),
+        presigner,
     };
-    let application = Application::build(configuration).await;
+    let application = Application::build_with_storage(configuration, storage).await;
     let address = format!("http://{}", application.address());
     let port = application.port();
 
-    let _ = tokio::spawn(async move { application.run().await });
+    tokio::spawn(async move { application.run().await });
 
     TestApp { address, port }
 }
+
+// Contributors created by the admin, users share the password `secret`.
+pub async fn create_user(app: &TestApp, username: &str) {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/users", &app.address))
+        .basic_auth(ADMIN, Some("secret"))
+        .json(&serde_json::json!({ "username": username, "password": "secret" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+// Reason phrase of the status line, twine shows it when an upload fails.
+pub fn reason_phrase(response: &reqwest::Response) -> Option<String> {
+    response
+        .extensions()
+        .get::<hyper_client::ext::ReasonPhrase>()
+        .map(|reason| String::from_utf8_lossy(reason.as_bytes()).into_owned())
+}
+
+// Source distribution only holding its `PKG-INFO`, the summary changes its
+// content.
+fn sdist(project: &str, version: &str, summary: &str) -> Vec<u8> {
+    let metadata = format!(
+        "Metadata-Version: 2.1\nName: {}\nVersion: {}\nSummary: {}\n",
+        project, version, summary
+    );
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(metadata.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    archive
+        .append_data(
+            &mut header,
+            format!("{}-{}/PKG-INFO", project, version),
+            metadata.as_bytes(),
+        )
+        .unwrap();
+    archive.into_inner().unwrap().finish().unwrap()
+}
+
+// Wheel only holding its `METADATA` and `WHEEL` files.
+fn wheel(project: &str, version: &str) -> Vec<u8> {
+    let dist_info = format!("{}-{}.dist-info", project.replace('-', "_"), version);
+    let metadata = format!(
+        "Metadata-Version: 2.1\nName: {}\nVersion: {}\n",
+        project, version
+    );
+
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    archive
+        .start_file(format!("{}/METADATA", dist_info), options)
+        .unwrap();
+    archive.write_all(metadata.as_bytes()).unwrap();
+    archive
+        .start_file(format!("{}/WHEEL", dist_info), options)
+        .unwrap();
+    archive
+        .write_all(b"Wheel-Version: 1.0\nTag: py3-none-any\n")
+        .unwrap();
+    archive.finish().unwrap().into_inner()
+}
+
+// Upload the file of the `content` part.
+pub async fn upload(
+    app: &TestApp,
+    path: &str,
+    username: &str,
+    project: &str,
+    version: &str,
+    filetype: &'static str,
+    part: Part,
+) -> reqwest::Response {
+    let form = Form::new()
+        .text(":action", "file_upload")
+        .text("protocol_version", "1")
+        .text("name", project.to_owned())
+        .text("version", version.to_owned())
+        .text("filetype", filetype)
+        .text("metadata_version", "2.1")
+        .part("content", part);
+
+    reqwest::Client::new()
+        .post(format!("{}{}", &app.address, path))
+        .basic_auth(username, Some("secret"))
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+// Upload an sdist of the project to the simple API under `path`.
+pub async fn upload_sdist(
+    app: &TestApp,
+    path: &str,
+    username: &str,
+    project: &str,
+    version: &str,
+) -> reqwest::Response {
+    upload_sdist_with_summary(app, path, username, project, version, "").await
+}
+
+// Uploads of the same version with different summaries have the same
+// filename but a different content.
+pub async fn upload_sdist_with_summary(
+    app: &TestApp,
+    path: &str,
+    username: &str,
+    project: &str,
+    version: &str,
+    summary: &str,
+) -> reqwest::Response {
+    let filename = format!("{}-{}.tar.gz", project.replace('-', "_"), version);
+    let part = Part::bytes(sdist(project, version, summary)).file_name(filename);
+
+    upload(app, path, username, project, version, "sdist", part).await
+}
+
+// Upload a `py3-none-any` wheel of the project to the simple API under `path`.
+#[allow(dead_code)]
+pub async fn upload_wheel(
+    app: &TestApp,
+    path: &str,
+    username: &str,
+    project: &str,
+    version: &str,
+) -> reqwest::Response {
+    let filename = format!("{}-{}-py3-none-any.whl", project.replace('-', "_"), version);
+    let part = Part::bytes(wheel(project, version)).file_name(filename);
+
+    upload(app, path, username, project, version, "bdist_wheel", part).await
+}
@@ -0,0 +1,296 @@
+use serde_json::{json, Value};
+
+use crate::helpers::{create_user, spawn_app, upload_sdist, TestApp, ADMIN};
+
+async fn set_index(app: &TestApp, username: &str, index: &str, definition: Value) -> u16 {
+    reqwest::Client::new()
+        .put(format!("{}/api/indexes/{}", &app.address, index))
+        .basic_auth(username, Some("secret"))
+        .json(&definition)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+async fn get_json(app: &TestApp, path: &str) -> Value {
+    reqwest::Client::new()
+        .get(format!("{}{}", &app.address, path))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+fn project_names(list: &Value) -> Vec<String> {
+    list["projects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn named_indexes_inherit_their_parents_and_isolate_uploads() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let index = format!("team-{}", suffix);
+    let shared = format!("shared-{}", suffix);
+    let team = format!("team-{}", suffix);
+
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let status = set_index(&app, ADMIN, &index, json!({"parents": ["root"]})).await;
+    assert_eq!(status, 200);
+
+    let response = upload_sdist(&app, "/simple", &username, &shared, "1.0").await;
+    assert!(response.status().is_success());
+    let index_path = format!("/indexes/{}/simple", index);
+    let response = upload_sdist(&app, &index_path, &username, &team, "1.0").await;
+    assert!(response.status().is_success());
+
+    let projects = project_names(&get_json(&app, &index_path).await);
+    assert!(projects.contains(&shared));
+    assert!(projects.contains(&team));
+
+    let projects = project_names(&get_json(&app, "/simple").await);
+    assert!(projects.contains(&shared));
+    assert!(!projects.contains(&team));
+
+    // Files of the parents are linked and served under the index.
+    let page = get_json(&app, &format!("{}/{}/", index_path, shared)).await;
+    let url = page["files"][0]["url"].as_str().unwrap().to_owned();
+    assert!(url.starts_with(&index_path));
+    let response = reqwest::get(format!("{}{}", &app.address, url))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let definition = json!({
+        "parents": ["root"],
+        "overrides": [{"project": shared, "rule": "block"}]
+    });
+    assert_eq!(set_index(&app, ADMIN, &index, definition).await, 200);
+
+    let page = get_json(&app, &format!("{}/{}/", index_path, shared)).await;
+    assert!(page["files"].as_array().unwrap().is_empty());
+    let projects = project_names(&get_json(&app, &index_path).await);
+    assert!(!projects.contains(&shared));
+}
+
+async fn xmlrpc(app: &TestApp, method: &str, params: &str) -> String {
+    let body = format!(
+        r#"<?xml version="1.0"?><methodCall><methodName>{}</methodName><params>{}</params></methodCall>"#,
+        method, params
+    );
+
+    reqwest::Client::new()
+        .post(format!("{}/pypi", &app.address))
+        .header("Content-Type", "text/xml")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn named_index_changes_stay_out_of_the_root_changelog() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let index = format!("team-{}", suffix);
+    let shared = format!("shared-{}", suffix);
+    let team = format!("team-{}", suffix);
+
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    assert_eq!(
+        set_index(&app, ADMIN, &index, json!({"parents": ["root"]})).await,
+        200
+    );
+
+    let serial = xmlrpc(&app, "changelog_last_serial", "").await;
+    let serial = serial
+        .split("<int>")
+        .nth(1)
+        .and_then(|s| s.split("</int>").next())
+        .unwrap()
+        .to_owned();
+
+    let index_path = format!("/indexes/{}/simple", index);
+    let response = upload_sdist(&app, &index_path, &username, &team, "1.0").await;
+    assert!(response.status().is_success());
+    let response = upload_sdist(&app, "/simple", &username, &shared, "1.0").await;
+    assert!(response.status().is_success());
+
+    let params = format!("<param><value><int>{}</int></value></param>", serial);
+    let changelog = xmlrpc(&app, "changelog_since_serial", &params).await;
+    assert!(changelog.contains(&shared));
+    assert!(!changelog.contains(&team));
+
+    let projects = xmlrpc(&app, "list_packages_with_serial", "").await;
+    assert!(projects.contains(&shared));
+    assert!(!projects.contains(&team));
+}
+
+#[tokio::test]
+async fn invalid_index_definitions_are_rejected() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let (first, second) = (format!("first-{}", suffix), format!("second-{}", suffix));
+
+    // Only the admins define indexes.
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    let definition = json!({"parents": ["root"]});
+    assert_eq!(set_index(&app, &username, &first, definition).await, 403);
+
+    let unknown = json!({"parents": [format!("unknown-{}", suffix)]});
+    assert_eq!(set_index(&app, ADMIN, &first, unknown).await, 400);
+
+    assert_eq!(
+        set_index(&app, ADMIN, &first, json!({"parents": ["root"]})).await,
+        200
+    );
+    assert_eq!(
+        set_index(&app, ADMIN, &second, json!({"parents": [first]})).await,
+        200
+    );
+
+    let cycle = json!({"parents": [second]});
+    assert_eq!(set_index(&app, ADMIN, &first, cycle).await, 400);
+
+    let response = reqwest::get(format!("{}/indexes/{}/simple/", &app.address, suffix))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn promoted_releases_keep_their_files() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let staging = format!("staging-{}", suffix);
+    let project = format!("release-{}", suffix);
+
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    let status = set_index(&app, ADMIN, &staging, json!({"parents": ["root"]})).await;
+    assert_eq!(status, 200);
+
+    let staging_path = format!("/indexes/{}/simple", staging);
+    let response = upload_sdist(&app, &staging_path, &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let page = get_json(&app, &format!("{}/{}/", staging_path, project)).await;
+    let staged = page["files"][0].clone();
+    let page = get_json(&app, &format!("/simple/{}/", project)).await;
+    assert!(page["files"].as_array().unwrap().is_empty());
+
+    let promote = |target: &str| {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/api/indexes/{}/projects/{}/releases/1.0/promote",
+                &app.address, staging, project
+            ))
+            .basic_auth(&username, Some("secret"))
+            .json(&json!({ "target": target }))
+            .send()
+    };
+
+    let response = promote("root").await.expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let promotion: Value = response.json().await.unwrap();
+    assert_eq!(promotion["promoted_by"], username.as_str());
+    assert_eq!(promotion["filenames"][0], staged["filename"]);
+
+    // The same file is served by the root index, and still seen from staging.
+    let page = get_json(&app, &format!("/simple/{}/", project)).await;
+    assert_eq!(page["files"][0]["filename"], staged["filename"]);
+    assert_eq!(page["files"][0]["hashes"], staged["hashes"]);
+    let page = get_json(&app, &format!("{}/{}/", staging_path, project)).await;
+    assert_eq!(page["files"][0]["hashes"], staged["hashes"]);
+
+    let response = promote("root").await.expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 400);
+
+    let promotions: Value = reqwest::Client::new()
+        .get(format!(
+            "{}/api/indexes/root/projects/{}/promotions",
+            &app.address, project
+        ))
+        .basic_auth(&username, Some("secret"))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(promotions[0]["source"], staging.as_str());
+    assert_eq!(promotions[0]["target"], "root");
+}
+
+#[tokio::test]
+async fn promotions_leave_the_source_index_untouched() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let staging = format!("staging-{}", suffix);
+    let production = format!("production-{}", suffix);
+    let project = format!("release-{}", suffix);
+
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    for index in [&staging, &production] {
+        let status = set_index(&app, ADMIN, index, json!({"parents": ["root"]})).await;
+        assert_eq!(status, 200);
+    }
+
+    let staging_path = format!("/indexes/{}/simple", staging);
+    let production_path = format!("/indexes/{}/simple", production);
+    let response = upload_sdist(&app, &staging_path, &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+    let staged =
+        get_json(&app, &format!("{}/{}/", staging_path, project)).await["files"][0].clone();
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/api/indexes/{}/projects/{}/releases/1.0/promote",
+            &app.address, staging, project
+        ))
+        .basic_auth(&username, Some("secret"))
+        .json(&json!({ "target": production }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    // Both indexes serve the same object, the root index doesn't see it.
+    let mut contents = Vec::new();
+    for path in [&staging_path, &production_path] {
+        let page = get_json(&app, &format!("{}/{}/", path, project)).await;
+        assert_eq!(page["files"][0]["filename"], staged["filename"]);
+        assert_eq!(page["files"][0]["hashes"], staged["hashes"]);
+
+        let response = reqwest::get(format!(
+            "{}{}",
+            &app.address,
+            page["files"][0]["url"].as_str().unwrap()
+        ))
+        .await
+        .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 200);
+        contents.push(response.bytes().await.unwrap());
+    }
+    assert_eq!(contents[0], contents[1]);
+
+    let page = get_json(&app, &format!("/simple/{}/", project)).await;
+    assert!(page["files"].as_array().unwrap().is_empty());
+}
@@ -1,2 +1,10 @@
 mod healthcheck;
 mod helpers;
+mod indexes;
+mod maintainers;
+mod pypi;
+mod simple;
+mod uploads;
+mod users;
+mod versions;
+mod yank;
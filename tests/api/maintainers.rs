@@ -0,0 +1,211 @@
+use serde_json::{json, Value};
+
+use crate::helpers::{connect_db, create_user, spawn_app, upload_sdist, TestApp, ADMIN};
+
+fn maintainers_url(app: &TestApp, project: &str) -> String {
+    format!(
+        "{}/api/indexes/root/projects/{}/maintainers",
+        &app.address, project
+    )
+}
+
+async fn add_maintainer(app: &TestApp, as_user: &str, project: &str, username: &str) -> u16 {
+    reqwest::Client::new()
+        .post(maintainers_url(app, project))
+        .basic_auth(as_user, Some("secret"))
+        .json(&json!({ "username": username }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+async fn get_maintainers(app: &TestApp, project: &str, username: &str) -> Value {
+    reqwest::Client::new()
+        .get(maintainers_url(app, project))
+        .basic_auth(username, Some("secret"))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn only_maintainers_upload_to_a_project() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("owned-{}", suffix);
+    let (owner, colleague) = (format!("owner-{}", suffix), format!("colleague-{}", suffix));
+    create_user(&app, &owner).await;
+    create_user(&app, &colleague).await;
+
+    // The uploader of a new project becomes its owner.
+    let response = upload_sdist(&app, "/simple", &owner, &project, "1.0").await;
+    assert!(response.status().is_success());
+    let maintainers = get_maintainers(&app, &project, &owner).await;
+    assert_eq!(maintainers, json!([{"username": owner, "role": "owner"}]));
+
+    let response = upload_sdist(&app, "/simple", &colleague, &project, "1.1").await;
+    assert_eq!(response.status().as_u16(), 403);
+    let yank = reqwest::Client::new()
+        .post(format!(
+            "{}/api/projects/{}/releases/1.0/yank",
+            &app.address, project
+        ))
+        .basic_auth(&colleague, Some("secret"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(yank.status().as_u16(), 403);
+
+    assert_eq!(
+        add_maintainer(&app, &colleague, &project, &colleague).await,
+        403
+    );
+    assert_eq!(
+        add_maintainer(&app, &owner, &project, &colleague).await,
+        200
+    );
+    let response = upload_sdist(&app, "/simple", &colleague, &project, "1.1").await;
+    assert!(response.status().is_success());
+
+    // The previous owner stays a maintainer, and the owner can't be removed.
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/indexes/root/projects/{}/owner",
+            &app.address, project
+        ))
+        .basic_auth(&owner, Some("secret"))
+        .json(&json!({ "username": colleague }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let maintainers = get_maintainers(&app, &project, &owner).await;
+    assert_eq!(
+        maintainers,
+        json!([
+            {"username": colleague, "role": "owner"},
+            {"username": owner, "role": "maintainer"}
+        ])
+    );
+
+    let remove = |as_user: &str, username: &str| {
+        reqwest::Client::new()
+            .delete(format!("{}/{}", maintainers_url(&app, &project), username))
+            .basic_auth(as_user, Some("secret"))
+            .send()
+    };
+    let response = remove(&colleague, &colleague).await.unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    let response = remove(&colleague, &owner).await.unwrap();
+    assert!(response.status().is_success());
+
+    let response = upload_sdist(&app, "/simple", &owner, &project, "1.2").await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn projects_created_before_ownership_are_not_claimed_by_uploads() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("legacy-{}", suffix);
+    let (author, stranger) = (format!("author-{}", suffix), format!("stranger-{}", suffix));
+    create_user(&app, &author).await;
+    create_user(&app, &stranger).await;
+
+    // Projects created before ownership have releases but no roles.
+    let response = upload_sdist(&app, "/simple", &author, &project, "1.0").await;
+    assert!(response.status().is_success());
+    sqlx::query(
+        r#"
+        DELETE FROM project_roles
+        WHERE project_id = (SELECT id FROM projects WHERE normalized_name = $1)
+        "#,
+    )
+    .bind(&project)
+    .execute(&connect_db().await)
+    .await
+    .unwrap();
+
+    for username in [&stranger, &author] {
+        let response = upload_sdist(&app, "/simple", username, &project, "1.1").await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+    assert_eq!(get_maintainers(&app, &project, &author).await, json!([]));
+
+    // An admin hands the project over to its owner.
+    let response = reqwest::Client::new()
+        .put(format!(
+            "{}/api/indexes/root/projects/{}/owner",
+            &app.address, project
+        ))
+        .basic_auth(ADMIN, Some("secret"))
+        .json(&json!({ "username": author }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    let response = upload_sdist(&app, "/simple", &author, &project, "1.1").await;
+    assert!(response.status().is_success());
+    let response = upload_sdist(&app, "/simple", &stranger, &project, "1.2").await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn failed_uploads_do_not_claim_ownership() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("claim-{}", suffix);
+    let index = format!("other-{}", suffix);
+    let (author, stranger) = (format!("author-{}", suffix), format!("stranger-{}", suffix));
+    create_user(&app, &author).await;
+    create_user(&app, &stranger).await;
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/api/indexes/{}", &app.address, index))
+        .basic_auth(ADMIN, Some("secret"))
+        .json(&json!({"parents": ["root"]}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let response = upload_sdist(&app, "/simple", &author, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    // An empty project without owner, its upload fails as the file belongs
+    // to the root index.
+    sqlx::query(
+        r#"
+        INSERT INTO projects (name, normalized_name, index_id)
+        SELECT $1, $1, id FROM indexes WHERE name = $2
+        "#,
+    )
+    .bind(&project)
+    .bind(&index)
+    .execute(&connect_db().await)
+    .await
+    .unwrap();
+
+    let index_path = format!("/indexes/{}/simple", index);
+    let response = upload_sdist(&app, &index_path, &stranger, &project, "1.0").await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let maintainers: Value = reqwest::Client::new()
+        .get(format!(
+            "{}/api/indexes/{}/projects/{}/maintainers",
+            &app.address, index, project
+        ))
+        .basic_auth(&stranger, Some("secret"))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(maintainers, json!([]));
+}
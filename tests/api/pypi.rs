@@ -0,0 +1,171 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{create_user, spawn_app, upload_sdist, upload_wheel, TestApp};
+
+async fn get_json(app: &TestApp, path: &str) -> Value {
+    let response = reqwest::Client::new()
+        .get(format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    response.json().await.unwrap()
+}
+
+fn filenames(files: &Value) -> Vec<&str> {
+    let mut filenames: Vec<&str> = files
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["filename"].as_str().unwrap())
+        .collect();
+    filenames.sort();
+    filenames
+}
+
+#[tokio::test]
+async fn pypi_json_returns_404_for_unknown_projects() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    for path in ["unknown-project/json", "unknown-project/1.0/json"] {
+        let response = client
+            .get(format!("{}/pypi/{}", &app.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert_eq!(response.status().as_u16(), 404);
+    }
+}
+
+#[tokio::test]
+async fn xmlrpc_returns_faults_for_unknown_methods() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/pypi", &app.address))
+        .header("Content-Type", "text/xml")
+        .body(r#"<?xml version="1.0"?><methodCall><methodName>search</methodName></methodCall>"#)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<fault>"));
+    assert!(body.contains("<int>-32601</int>"));
+}
+
+#[tokio::test]
+async fn pypi_json_describes_projects_and_releases() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("json-{}", suffix);
+    let sdist = |version: &str| format!("json_{}-{}.tar.gz", suffix, version);
+    let wheel = format!("json_{}-1.0-py3-none-any.whl", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+    let response = upload_wheel(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+    let serial = get_json(&app, &format!("/pypi/{}/json", project)).await["last_serial"]
+        .as_i64()
+        .unwrap();
+    assert!(serial > 0);
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.1").await;
+    assert!(response.status().is_success());
+
+    // The project endpoint describes the latest release and lists them all.
+    let body = get_json(&app, &format!("/pypi/{}/json", project)).await;
+    assert_eq!(body["info"]["name"], project.as_str());
+    assert_eq!(body["info"]["version"], "1.1");
+    assert!(body["last_serial"].as_i64().unwrap() > serial);
+    assert_eq!(
+        filenames(&body["releases"]["1.0"]),
+        vec![wheel.as_str(), sdist("1.0").as_str()]
+    );
+    assert_eq!(
+        filenames(&body["releases"]["1.1"]),
+        vec![sdist("1.1").as_str()]
+    );
+    assert_eq!(filenames(&body["urls"]), vec![sdist("1.1").as_str()]);
+    assert_eq!(body["urls"][0]["packagetype"], "sdist");
+
+    // Digests are the ones of the served file.
+    let url = body["urls"][0]["url"].as_str().unwrap();
+    let content = reqwest::get(format!("{}{}", &app.address, url))
+        .await
+        .expect("Failed to execute request.")
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(
+        body["urls"][0]["digests"]["sha256"],
+        format!("{:x}", Sha256::digest(&content)).as_str()
+    );
+    assert_eq!(body["urls"][0]["size"], content.len());
+
+    // The release endpoint describes the given release, without the others.
+    let body = get_json(&app, &format!("/pypi/{}/1.0/json", project)).await;
+    assert_eq!(body["info"]["version"], "1.0");
+    assert!(body.get("releases").is_none());
+    assert_eq!(
+        filenames(&body["urls"]),
+        vec![wheel.as_str(), sdist("1.0").as_str()]
+    );
+    assert!(body["last_serial"].as_i64().unwrap() > serial);
+}
+
+#[tokio::test]
+async fn latest_release_is_the_latest_final_release_in_every_api() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("latest-{}", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    for version in ["1.0", "2.0rc1"] {
+        let response = upload_sdist(&app, "/simple", &username, &project, version).await;
+        assert!(response.status().is_success());
+    }
+
+    let body = get_json(&app, &format!("/pypi/{}/json", project)).await;
+    assert_eq!(body["info"]["version"], "1.0");
+
+    let call = |show_hidden: bool| {
+        format!(
+            r#"<?xml version="1.0"?><methodCall><methodName>package_releases</methodName><params><param><value><string>{}</string></value></param><param><value><boolean>{}</boolean></value></param></params></methodCall>"#,
+            project, show_hidden as u8
+        )
+    };
+    let client = reqwest::Client::new();
+    let mut releases = Vec::new();
+    for show_hidden in [false, true] {
+        let body = client
+            .post(format!("{}/pypi", &app.address))
+            .header("Content-Type", "text/xml")
+            .body(call(show_hidden))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap();
+        releases.push(body);
+    }
+
+    assert!(releases[0].contains("<string>1.0</string>"));
+    assert!(!releases[0].contains("2.0rc1"));
+    let (new, old) = (
+        releases[1].find("<string>2.0rc1</string>").unwrap(),
+        releases[1].find("<string>1.0</string>").unwrap(),
+    );
+    assert!(new < old);
+}
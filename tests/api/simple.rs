@@ -0,0 +1,383 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::{routing::get, Json, Router};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{
+    create_user, spawn_app, spawn_app_with_mirror, spawn_app_with_presigner,
+    spawn_app_with_upstream, upload_sdist, upload_wheel, TestApp,
+};
+
+#[tokio::test]
+async fn simple_index_serves_pep_691_json() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/simple", &app.address))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.pypi.simple.v1+json"
+    );
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["meta"]["api-version"], "1.1");
+    assert!(body["projects"].is_array());
+}
+
+#[tokio::test]
+async fn simple_index_format_query_overrides_accept() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!(
+            "{}/simple?format=application/vnd.pypi.simple.v1%2Bhtml",
+            &app.address
+        ))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.pypi.simple.v1+html"
+    );
+}
+
+#[tokio::test]
+async fn simple_index_rejects_unsupported_content_type() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/simple", &app.address))
+        .header("Accept", "application/xml")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 406);
+}
+
+#[tokio::test]
+async fn download_returns_404_for_unknown_files() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!(
+            "{}/simple/unknown-project/unknown_project-1.0.tar.gz",
+            &app.address
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+// Link of the single file of the project on its PEP 691 page.
+async fn file_url(app: &TestApp, project: &str) -> String {
+    let page: serde_json::Value = reqwest::Client::new()
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+
+    format!(
+        "{}{}",
+        &app.address,
+        page["files"][0]["url"].as_str().unwrap()
+    )
+}
+
+#[tokio::test]
+async fn downloads_are_redirected_to_presigned_urls() {
+    let app = spawn_app_with_presigner().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("presigned-{}", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = client
+        .get(file_url(&app, &project).await)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 302);
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with(&format!(
+        "https://s3.us-east-1.amazonaws.com/nest/simple-index/{}/presigned_{}-1.0.tar.gz?",
+        project, suffix
+    )));
+    assert!(location.contains("X-Amz-Expires=300"));
+    assert_eq!(response.headers()["cache-control"], "no-store");
+}
+
+#[tokio::test]
+async fn downloads_are_proxied_without_presigner() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("proxied-{}", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = client
+        .get(file_url(&app, &project).await)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("location").is_none());
+    let content = response.bytes().await.unwrap();
+    assert_eq!(&content[..2], b"\x1f\x8b");
+}
+
+// Stand-in of an upstream index serving a single file, returns its address
+// and the number of times the file was downloaded.
+async fn spawn_upstream(
+    project: &str,
+    filename: &str,
+    content: &str,
+) -> (String, Arc<AtomicUsize>) {
+    let downloads = Arc::new(AtomicUsize::new(0));
+    let page = serde_json::json!({
+        "meta": {"api-version": "1.1", "_last-serial": 1},
+        "name": project,
+        "files": [{
+            "filename": filename,
+            "url": format!("../../files/{}", filename),
+            "hashes": {"sha256": format!("{:x}", Sha256::digest(content))},
+        }],
+    });
+
+    let content = content.to_owned();
+    let counter = downloads.clone();
+    let app = Router::new()
+        .route(
+            &format!("/simple/{}/", project),
+            get(move || async move { Json(page) }),
+        )
+        .route(
+            &format!("/files/{}", filename),
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                content
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}/simple/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    (address, downloads)
+}
+
+#[tokio::test]
+async fn upstream_files_are_cached_on_first_download() {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("upstream-{}", suffix);
+    let filename = format!("upstream_{}-1.0.tar.gz", suffix);
+    let content = format!("content of {}", filename);
+
+    let (upstream, downloads) = spawn_upstream(&project, &filename, &content).await;
+    let app = spawn_app_with_upstream(Some(upstream)).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["files"][0]["filename"], filename.as_str());
+    assert_eq!(body["versions"][0], "1.0");
+
+    for _ in 0..2 {
+        let response = client
+            .get(format!("{}/simple/{}/{}", &app.address, project, filename))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert!(response.status().is_success());
+        assert_eq!(response.text().await.unwrap(), content);
+    }
+
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn uploads_of_upstream_projects_are_rejected() {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("upstream-{}", suffix);
+    let filename = format!("upstream_{}-1.0.tar.gz", suffix);
+
+    let (upstream, _) = spawn_upstream(&project, &filename, "upstream content").await;
+    let app = spawn_app_with_upstream(Some(upstream)).await;
+
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "2.0").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response.text().await.unwrap().contains("claim"));
+}
+
+#[tokio::test]
+async fn mirrored_projects_are_copied_ahead_of_time() {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("mirror-{}", suffix);
+    let filename = format!("mirror_{}-1.0.tar.gz", suffix);
+    let content = format!("content of {}", filename);
+
+    let (upstream, downloads) = spawn_upstream(&project, &filename, &content).await;
+    let app = spawn_app_with_mirror(upstream, vec![project.clone()]).await;
+    let client = reqwest::Client::new();
+
+    // The mirror runs in the background, the file is listed by the JSON API
+    // once it is copied.
+    let mut copied = false;
+    for _ in 0..50 {
+        let response = client
+            .get(format!("{}/pypi/{}/json", &app.address, project))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        if response.status().is_success() {
+            let body: serde_json::Value = response.json().await.unwrap();
+            if body["urls"].as_array().is_some_and(|urls| !urls.is_empty()) {
+                copied = true;
+                break;
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(copied);
+
+    let response = client
+        .get(format!("{}/simple/{}/{}", &app.address, project, filename))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(response.text().await.unwrap(), content);
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn uploads_are_downloaded_through_the_normalized_name() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let project = format!("Mixed_Case.{}", suffix);
+    let filename = format!("Mixed_Case.{}-1.0.tar.gz", suffix);
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/simple/mixed-case-{}/{}",
+            &app.address, suffix, filename
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert!(!response.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn wheel_core_metadata_is_served_as_text() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let project = format!("wheel-{}", suffix);
+    let filename = format!("wheel_{}-1.0-py3-none-any.whl", suffix);
+    let metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: 1.0\n", project);
+    let digest = format!("{:x}", Sha256::digest(&metadata));
+
+    let response = upload_wheel(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let response = client
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["files"][0]["filename"], filename.as_str());
+    assert_eq!(body["files"][0]["core-metadata"]["sha256"], digest.as_str());
+    assert_eq!(
+        body["files"][0]["dist-info-metadata"]["sha256"],
+        digest.as_str()
+    );
+
+    let response = client
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let page = response.text().await.unwrap();
+    assert!(page.contains(&format!("data-dist-info-metadata=\"sha256={}\"", digest)));
+
+    let response = client
+        .get(format!(
+            "{}/simple/{}/{}.metadata",
+            &app.address, project, filename
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    assert!(response.headers().get("content-disposition").is_none());
+    assert_eq!(response.text().await.unwrap(), metadata);
+}
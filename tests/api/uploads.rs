@@ -0,0 +1,157 @@
+use reqwest::multipart::Part;
+use serde_json::{json, Value};
+
+use crate::helpers::{
+    create_user, reason_phrase, spawn_app, upload, upload_sdist, upload_sdist_with_summary,
+    TestApp, ADMIN,
+};
+
+#[tokio::test]
+async fn failed_uploads_report_twine_status_and_reason() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("status-{}", suffix);
+    let filename = format!("status_{}-1.0.tar.gz", suffix);
+    let (owner, stranger) = (format!("owner-{}", suffix), format!("stranger-{}", suffix));
+    create_user(&app, &owner).await;
+    create_user(&app, &stranger).await;
+
+    // The metadata of a corrupted archive can't be read.
+    let part = Part::bytes(b"not an archive".to_vec()).file_name(filename.clone());
+    let response = upload(&app, "/simple", &owner, &project, "1.0", "sdist", part).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let reason = reason_phrase(&response).unwrap();
+    assert_eq!(reason, response.text().await.unwrap());
+
+    let response = upload_sdist(&app, "/simple", &owner, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let response =
+        upload_sdist_with_summary(&app, "/simple", &owner, &project, "1.0", "changed").await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        reason_phrase(&response).unwrap(),
+        format!("File already exists ({}).", filename)
+    );
+
+    let response = upload_sdist(&app, "/simple", &stranger, &project, "1.1").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        reason_phrase(&response).unwrap(),
+        format!("The user isn't allowed to upload to project {}.", project)
+    );
+}
+
+// Session cookie of the admin, project settings are changed from the manage
+// pages.
+async fn admin_cookie(app: &TestApp) -> String {
+    let response = reqwest::Client::new()
+        .post(format!("{}/manage/login", &app.address))
+        .form(&[("username", ADMIN), ("password", "secret")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    cookie.split(';').next().unwrap().to_owned()
+}
+
+async fn set_file_overwrite(app: &TestApp, cookie: &str, project: &str, allow: bool) {
+    let form: &[(&str, &str)] = if allow {
+        &[("allow_file_overwrite", "on")]
+    } else {
+        &[]
+    };
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/manage/projects/{}/settings",
+            &app.address, project
+        ))
+        .header("Cookie", cookie)
+        .form(form)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+}
+
+async fn file_sha256(app: &TestApp, project: &str) -> Value {
+    let response = reqwest::Client::new()
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .header("Accept", "application/vnd.pypi.simple.v1+json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let body: Value = response.json().await.unwrap();
+    body["files"][0]["hashes"]["sha256"].clone()
+}
+
+#[tokio::test]
+async fn files_are_immutable_unless_the_project_allows_overwrites() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("immutable-{}", suffix);
+    let filename = format!("immutable_{}-1.0.tar.gz", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+    let sha256 = file_sha256(&app, &project).await;
+
+    // Identical uploads succeed, so `twine upload --skip-existing` and
+    // retries go through.
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    let upload_changed = |summary: &'static str| {
+        upload_sdist_with_summary(&app, "/simple", &username, &project, "1.0", summary)
+    };
+    let response = upload_changed("changed").await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        reason_phrase(&response).unwrap(),
+        format!("File already exists ({}).", filename)
+    );
+    assert_eq!(file_sha256(&app, &project).await, sha256);
+
+    let cookie = admin_cookie(&app).await;
+    set_file_overwrite(&app, &cookie, &project, true).await;
+    let response = upload_changed("changed").await;
+    assert!(response.status().is_success());
+    let overwritten = file_sha256(&app, &project).await;
+    assert_ne!(overwritten, sha256);
+
+    set_file_overwrite(&app, &cookie, &project, false).await;
+    let response = upload_changed("changed again").await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(file_sha256(&app, &project).await, overwritten);
+}
+
+#[tokio::test]
+async fn filenames_are_unique_across_projects() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("unique-{}", suffix);
+    let index = format!("other-{}", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/api/indexes/{}", &app.address, index))
+        .basic_auth(ADMIN, Some("secret"))
+        .json(&json!({"parents": ["root"]}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    // The same file in the project of another index isn't a re-upload.
+    let index_path = format!("/indexes/{}/simple", index);
+    let response = upload_sdist(&app, &index_path, &username, &project, "1.0").await;
+    assert_eq!(response.status().as_u16(), 409);
+}
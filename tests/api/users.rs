@@ -0,0 +1,53 @@
+use serde_json::{json, Value};
+
+use crate::helpers::{create_user, spawn_app, TestApp, ADMIN};
+
+async fn post_user(app: &TestApp, as_user: &str, user: Value) -> u16 {
+    reqwest::Client::new()
+        .post(format!("{}/api/users", &app.address))
+        .basic_auth(as_user, Some("secret"))
+        .json(&user)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+async fn set_role(app: &TestApp, as_user: &str, username: &str, role: &str) -> u16 {
+    reqwest::Client::new()
+        .put(format!("{}/api/users/{}/role", &app.address, username))
+        .basic_auth(as_user, Some("secret"))
+        .json(&json!({ "role": role }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn only_admins_manage_users() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let contributor = format!("contributor-{}", suffix);
+    let other = format!("other-{}", suffix);
+    create_user(&app, &contributor).await;
+
+    let user = json!({"username": other, "password": "secret"});
+    assert_eq!(post_user(&app, &contributor, user.clone()).await, 403);
+    assert_eq!(post_user(&app, ADMIN, user.clone()).await, 201);
+    assert_eq!(post_user(&app, ADMIN, user).await, 409);
+
+    assert_eq!(set_role(&app, &contributor, &other, "admin").await, 403);
+    assert_eq!(set_role(&app, ADMIN, &contributor, "admin").await, 200);
+
+    // The promoted user is now an admin.
+    let user = json!({"username": format!("third-{}", suffix), "password": "secret"});
+    assert_eq!(post_user(&app, &contributor, user).await, 201);
+
+    assert_eq!(
+        set_role(&app, ADMIN, &format!("unknown-{}", suffix), "admin").await,
+        404
+    );
+}
@@ -0,0 +1,97 @@
+use crate::helpers::{connect_db, create_user, spawn_app, upload_sdist};
+
+#[tokio::test]
+async fn canonical_versions_are_backfilled_on_startup() {
+    let app = spawn_app().await;
+    let db = connect_db().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("versions-{}", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+
+    let response = upload_sdist(&app, "/simple", &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    // Releases saved before canonical versions kept their version as is, so
+    // `1.0` and `1.0.0` could be different releases.
+    sqlx::query(
+        r#"
+        WITH legacy AS (
+            UPDATE releases
+            SET canonical_version = version
+            WHERE project_id = (SELECT id FROM projects WHERE normalized_name = $1)
+            RETURNING project_id
+        )
+        INSERT INTO releases (version, canonical_version, project_id)
+        SELECT v, v, project_id
+        FROM legacy, UNNEST(ARRAY['1.0.0-rc1', '1.0-1', '1.0.0']) AS v
+        "#,
+    )
+    .bind(&project)
+    .execute(&db)
+    .await
+    .unwrap();
+
+    // The backfill runs once, on the first startup after the upgrade.
+    sqlx::query("DELETE FROM data_migrations WHERE name = 'canonical-versions'")
+        .execute(&db)
+        .await
+        .unwrap();
+    spawn_app().await;
+
+    let releases: Vec<(String, String, bool)> = sqlx::query_as(
+        r#"
+        SELECT r.version, r.canonical_version, r.is_prerelease
+        FROM releases r
+        JOIN projects p ON p.id = r.project_id
+        WHERE p.normalized_name = $1
+        ORDER BY r.id
+        "#,
+    )
+    .bind(&project)
+    .fetch_all(&db)
+    .await
+    .unwrap();
+
+    // The oldest release keeps the canonical version they share.
+    let expected = [
+        ("1.0", "1", false),
+        ("1.0.0-rc1", "1rc1", true),
+        ("1.0-1", "1.post1", false),
+        ("1.0.0", "1.0.0", false),
+    ];
+    let expected: Vec<(String, String, bool)> = expected
+        .iter()
+        .map(|(v, c, p)| (v.to_string(), c.to_string(), *p))
+        .collect();
+    assert_eq!(releases, expected);
+
+    // Once recorded, the backfill doesn't scan the releases again.
+    sqlx::query(
+        r#"
+        UPDATE releases
+        SET canonical_version = version
+        WHERE version = '1.0.0-rc1'
+            AND project_id = (SELECT id FROM projects WHERE normalized_name = $1)
+        "#,
+    )
+    .bind(&project)
+    .execute(&db)
+    .await
+    .unwrap();
+    spawn_app().await;
+
+    let canonical_version: String = sqlx::query_scalar(
+        r#"
+        SELECT r.canonical_version
+        FROM releases r
+        JOIN projects p ON p.id = r.project_id
+        WHERE p.normalized_name = $1 AND r.version = '1.0.0-rc1'
+        "#,
+    )
+    .bind(&project)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert_eq!(canonical_version, "1.0.0-rc1");
+}
@@ -0,0 +1,210 @@
+use serde_json::{json, Value};
+
+use crate::helpers::{create_user, spawn_app, upload_sdist, TestApp, ADMIN};
+
+async fn post_yank(app: &TestApp, username: &str, path: &str, reason: Option<&str>) {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/api/projects/{}", &app.address, path))
+        .basic_auth(username, Some("secret"));
+    if let Some(reason) = reason {
+        request = request.json(&json!({ "reason": reason }));
+    }
+
+    let response = request.send().await.expect("Failed to execute request.");
+    assert!(response.status().is_success());
+}
+
+async fn get_json(app: &TestApp, path: &str, accept: &str) -> Value {
+    reqwest::Client::new()
+        .get(format!("{}{}", &app.address, path))
+        .header("Accept", accept)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+// PEP 691 `yanked` of the file, and the `yanked` and `yanked_reason` of the
+// file in the JSON API.
+async fn file_yanked(app: &TestApp, project: &str, filename: &str) -> (Value, Value, Value) {
+    let simple = get_json(
+        app,
+        &format!("/simple/{}/", project),
+        "application/vnd.pypi.simple.v1+json",
+    )
+    .await;
+    let simple_file = simple["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["filename"] == filename)
+        .unwrap()
+        .clone();
+
+    let pypi = get_json(app, &format!("/pypi/{}/json", project), "application/json").await;
+    let pypi_file = pypi["releases"]
+        .as_object()
+        .unwrap()
+        .values()
+        .flat_map(|files| files.as_array().unwrap().clone())
+        .find(|f| f["filename"] == filename)
+        .unwrap();
+
+    (
+        simple_file["yanked"].clone(),
+        pypi_file["yanked"].clone(),
+        pypi_file["yanked_reason"].clone(),
+    )
+}
+
+#[tokio::test]
+async fn yanked_releases_and_files_are_flagged_with_their_reason() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let project = format!("yank-{}", suffix);
+    let (old, new) = (
+        format!("yank_{}-1.0.tar.gz", suffix),
+        format!("yank_{}-1.1.tar.gz", suffix),
+    );
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    for version in ["1.0", "1.1"] {
+        let response = upload_sdist(&app, "/simple", &username, &project, version).await;
+        assert!(response.status().is_success());
+    }
+
+    let not_yanked = (json!(false), json!(false), Value::Null);
+    assert_eq!(file_yanked(&app, &project, &old).await, not_yanked);
+
+    // A yanked release yanks its files, the JSON API flags the release too.
+    let release = format!("{}/releases/1.0", project);
+    post_yank(
+        &app,
+        &username,
+        &format!("{}/yank", release),
+        Some("broken"),
+    )
+    .await;
+    assert_eq!(
+        file_yanked(&app, &project, &old).await,
+        (json!("broken"), json!(true), json!("broken"))
+    );
+    assert_eq!(file_yanked(&app, &project, &new).await, not_yanked);
+    let info = get_json(
+        &app,
+        &format!("/pypi/{}/1.0/json", project),
+        "application/json",
+    )
+    .await;
+    assert_eq!(info["info"]["yanked"], true);
+    assert_eq!(info["info"]["yanked_reason"], "broken");
+
+    let page = reqwest::Client::new()
+        .get(format!("{}/simple/{}/", &app.address, project))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains(r#"data-yanked="broken""#));
+
+    post_yank(&app, &username, &format!("{}/unyank", release), None).await;
+    assert_eq!(file_yanked(&app, &project, &old).await, not_yanked);
+
+    // A yanked file doesn't yank its release, its own reason wins over the
+    // one of the release.
+    let file = format!("{}/files/{}", project, new);
+    post_yank(
+        &app,
+        &username,
+        &format!("{}/yank", file),
+        Some("bad build"),
+    )
+    .await;
+    assert_eq!(
+        file_yanked(&app, &project, &new).await,
+        (json!("bad build"), json!(true), json!("bad build"))
+    );
+    let info = get_json(
+        &app,
+        &format!("/pypi/{}/1.1/json", project),
+        "application/json",
+    )
+    .await;
+    assert_eq!(info["info"]["yanked"], false);
+
+    let release = format!("{}/releases/1.1", project);
+    post_yank(&app, &username, &format!("{}/yank", release), Some("old")).await;
+    assert_eq!(
+        file_yanked(&app, &project, &new).await,
+        (json!("bad build"), json!(true), json!("bad build"))
+    );
+
+    post_yank(&app, &username, &format!("{}/unyank", file), None).await;
+    assert_eq!(
+        file_yanked(&app, &project, &new).await,
+        (json!("old"), json!(true), json!("old"))
+    );
+    post_yank(&app, &username, &format!("{}/unyank", release), None).await;
+    assert_eq!(file_yanked(&app, &project, &new).await, not_yanked);
+}
+
+#[tokio::test]
+async fn files_of_named_indexes_are_yanked_under_their_index() {
+    let app = spawn_app().await;
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let index = format!("team-{}", suffix);
+    let project = format!("yank-{}", suffix);
+    let filename = format!("yank_{}-1.0.tar.gz", suffix);
+    let username = format!("user-{}", suffix);
+    create_user(&app, &username).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .put(format!("{}/api/indexes/{}", &app.address, index))
+        .basic_auth(ADMIN, Some("secret"))
+        .json(&json!({"parents": ["root"]}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let index_path = format!("/indexes/{}/simple", index);
+    let response = upload_sdist(&app, &index_path, &username, &project, "1.0").await;
+    assert!(response.status().is_success());
+
+    // The root index doesn't have the project.
+    let response = client
+        .post(format!(
+            "{}/api/projects/{}/files/{}/yank",
+            &app.address, project, filename
+        ))
+        .basic_auth(&username, Some("secret"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = client
+        .post(format!(
+            "{}/api/indexes/{}/projects/{}/files/{}/yank",
+            &app.address, index, project, filename
+        ))
+        .basic_auth(&username, Some("secret"))
+        .json(&json!({ "reason": "bad build" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    let page = get_json(
+        &app,
+        &format!("{}/{}/", index_path, project),
+        "application/vnd.pypi.simple.v1+json",
+    )
+    .await;
+    assert_eq!(page["files"][0]["filename"], filename);
+    assert_eq!(page["files"][0]["yanked"], "bad build");
+}